@@ -118,6 +118,7 @@ pub fn cargo_main<T: Plugin>(plugin: T) {
     // 执行 cargo 命令，并根据其退出状态退出程序
     let exit_status = cmd.status().expect("failed to wait for cargo?");
     tracing::info!("Finish to Exec {:?}", cmd);
+    plugin.after_cargo(&args.args, &target_dir);
     exit(exit_status.code().unwrap_or(-1));
 }
 
@@ -12,7 +12,7 @@ extern crate rustc_session;
 #[doc(hidden)]
 pub use cargo_metadata::camino::Utf8Path;
 pub use cargo_plugin::cargo_main;
-pub use plugin::{CrateFilter, Plugin, RustcPluginArgs};
+pub use plugin::{CrateFilter, Plugin, RustcPluginArgs, PLUGIN_ARGS};
 pub use rustc_plugin::rustc_main;
 
 mod cargo_plugin;
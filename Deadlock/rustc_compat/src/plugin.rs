@@ -50,6 +50,10 @@ pub trait Plugin: Sized {
     /// For example, you could pass a `--feature` flag here.
     fn modify_cargo(&self, _cargo: &mut Command, _args: &Self::Args) {}
 
+    /// Optionally run after `cargo` has finished, e.g. to collect results
+    /// the drivers left in the target directory.
+    fn after_cargo(&self, _args: &Self::Args, _target_dir: &Utf8Path) {}
+
     /// Executes the plugin with a set of compiler and plugin args.
     fn run(
         self,
@@ -1,8 +1,12 @@
 use std::{borrow::Borrow, fmt::format, rc::Rc, thread::current, usize};
 
-use alias::{graph::AliasGraph, node::EdgeLabel};
+use alias::{
+    graph::AliasGraph,
+    node::{AliasGraphNode, EdgeLabel, GraphNodeId},
+};
 
-use fact::VecFact;
+use fact::join;
+use isolate::isolate;
 use itertools::Itertools;
 use lockgraph::LockGraph;
 use rustc_hash::{FxHashMap, FxHashSet};
@@ -21,49 +25,100 @@ use rustc_middle::{
     ty::{self, Ty, TyCtxt, TyKind},
 };
 
-use rustc_middle::mir::{Body, Location, Statement, Terminator};
-use tools::{is_guard, is_mutex_method, is_smart_pointer};
+use rustc_middle::mir::{Body, Location, Operand, Statement, Terminator};
+use rustc_span::source_map::Spanned;
+use summary::{FnSummary, LockPath};
+use borrow::BorrowOp;
+use path::{contradicts, edge_conditions, intersect, stable_locals, Condition, MAX_PATH_CONDITIONS};
+use raw::RawUnlock;
+use condvar::{CondvarOp, CondvarOpKind};
+use thread::ThreadSpawn;
+use tools::{
+    callee_def_id, channel_op, condvar_op, is_future_poll, is_guard, is_mem_drop,
+    is_mutex_method, is_smart_pointer, is_thread_spawn, is_try_lock_method, lock_api_acquire, raw_lock_op,
+    refcell_borrow, static_ref, success_discriminant, ChannelOp, RawLockOp,
+};
 
-use crate::context::MyTcx;
+use crate::{context::MyTcx, option::AliasMode};
 
 pub mod alias;
+pub mod borrow;
 pub mod callgraph;
+pub mod condvar;
 pub mod fact;
+pub mod isolate;
 pub mod lock;
 pub mod lockgraph;
+pub mod path;
+pub mod prepass;
+pub mod raw;
+pub mod summary;
+pub mod thread;
 pub mod tools;
 mod visitor;
 pub struct LockSetAnalysis<'a, 'tcx> {
     my_tcx: &'a mut MyTcx<'tcx>,
 
-    // whole-program data
-    // a DefId + BasicBlock's index pair determines a bb
-    lock_set_facts: FxHashMap<DefId, FxHashMap<usize, LockSummary>>,
-
     // intra-analysis data
     // record all variable debug info in current function body
     // TODO: shadow nested scope
     var_debug_info: FxHashMap<usize, String>,
 
-    // lock graph
-    pub lock_graph: LockGraph,
+    // (held, acquired) lock pairs observed in each function, including the ones from callees
+    lock_orders: FxHashMap<DefId, FxHashSet<(Lock, Lock)>>,
+    // locks acquired by the callees of each function, in the caller's terms
+    callee_acquires: FxHashMap<DefId, FxHashSet<Lock>>,
+    // the result of a try-acquire -> where it is called, and the discriminant of a success
+    try_acquires: FxHashMap<(DefId, Local), (Location, u128)>,
+    // sites of the try-acquires, which callers and spawners never wait on
+    try_sites: FxHashSet<(DefId, Location)>,
+    // sites acquiring a lock without a guard, by a raw lock, a leaked guard or a callee
+    // leaving one held, whose lock is released explicitly
+    raw_sites: FxHashSet<(DefId, Location)>,
+    // the path-sensitive mode: the locals whose branches correlate in the current function,
+    // and the conditions holding on every path to each of its bbs
+    stable_locals: FxHashSet<Local>,
+    path_conditions: FxHashMap<usize, Vec<Condition>>,
+    // calls to a function whose analysis failed, whose summary is unknown
+    unknown_calls: usize,
 }
 
 impl<'a, 'tcx> LockSetAnalysis<'a, 'tcx> {
     pub fn new(my_tcx: &'a mut MyTcx<'tcx>) -> Self {
         Self {
             my_tcx,
-            lock_set_facts: FxHashMap::default(),
             var_debug_info: FxHashMap::default(),
-            lock_graph: LockGraph::new(),
+            lock_orders: FxHashMap::default(),
+            callee_acquires: FxHashMap::default(),
+            try_acquires: FxHashMap::default(),
+            try_sites: FxHashSet::default(),
+            raw_sites: FxHashSet::default(),
+            stable_locals: FxHashSet::default(),
+            path_conditions: FxHashMap::default(),
+            unknown_calls: 0,
         }
     }
 
     pub fn run_analysis(&mut self) {
         self.before_run();
 
-        self.intra_procedural_analysis();
-        self.inter_procedural_analysis();
+        // traverse the functions in a reversed topo order; a function's lock sets
+        // depend on the guards its callees return, so its callees are summarized first
+        for def_id in self.my_tcx.call_graph.topo.clone() {
+            // a function the alias analysis skipped has no alias nodes to track locks on
+            if !self.my_tcx.is_skipped(&def_id) {
+                let analyzed = isolate(|| {
+                    self.intra_procedural_analysis(def_id);
+                    self.inter_procedural_analysis(def_id);
+                });
+                if let Err(reason) = analyzed {
+                    self.my_tcx.skip(def_id, "lock set analysis", reason);
+                }
+            }
+            if self.my_tcx.is_skipped(&def_id) {
+                self.give_up(def_id);
+            }
+        }
 
         self.after_run();
     }
@@ -72,62 +127,86 @@ impl<'a, 'tcx> LockSetAnalysis<'a, 'tcx> {
         tracing::info!("Start alias analysis");
     }
 
-    fn after_run(&self) {
+    fn after_run(&mut self) {
         tracing::info!("Finish lock analysis");
+        let lock_nodes = self.lock_nodes();
+        let note = format!(
+            "{} lock node(s) acquired under the {} alias mode",
+            lock_nodes,
+            self.my_tcx.options.alias_mode
+        );
+        self.my_tcx.reporter.note(note);
+        if self.unknown_calls > 0 {
+            let note = format!(
+                "{} call(s) to functions with an unknown summary assumed to acquire no locks",
+                self.unknown_calls
+            );
+            self.my_tcx.reporter.note(note);
+        }
     }
 
-    pub fn print_lock_set_facts(&self) {
-        for (def_id, summaries) in &self.lock_set_facts {
-            println!("DefId: {:?}", def_id);
-            let mut keys: Vec<usize> = summaries.keys().cloned().collect();
-            keys.sort();
-            for index in keys {
-                let summary = summaries.get(&index).unwrap();
-                println!("  Index: {}", index);
-                for (i, lock_set) in summary.iter().enumerate() {
-                    println!("    Lock Summary {:?}:", i);
-                    for lock_fact in lock_set {
-                        let is_acq;
-                        if lock_fact.is_acquisition {
-                            is_acq = "+";
-                        } else {
-                            is_acq = "-";
-                        }
-                        println!(
-                            "      Lock: {:?}, Location: {:?}, {:?}, {:?}",
-                            lock_fact.lock, lock_fact.s_location, is_acq, lock_fact.state as i32
-                        );
-                    }
+    /// the alias nodes behind the acquired locks, fewer when locks are merged
+    fn lock_nodes(&self) -> usize {
+        let mut nodes = FxHashSet::default();
+        for facts in self.my_tcx.lock_set_facts.values() {
+            for lock_fact in facts.values().flatten().flatten() {
+                if !lock_fact.is_acquisition
+                    || self.my_tcx.borrow_sites.contains_key(&lock_fact.s_location)
+                {
+                    continue;
+                }
+                let id = lock_fact.lock.id.clone();
+                if let Some(node) = self.my_tcx.alias_graph.get_node(&id) {
+                    nodes.insert(node);
                 }
             }
         }
+        nodes.len()
     }
 
-    fn intra_procedural_analysis(&mut self) {
-        // traverse the functions in a reversed topo order
-        for def_id in self.my_tcx.call_graph.topo.clone() {
-            if self.my_tcx.tcx.is_mir_available(def_id) {
-                // each function is analyzed only once
-                let body = self.my_tcx.tcx.optimized_mir(def_id);
-                if def_id.is_local() && self.lock_set_facts.get(&def_id) == None {
-                    // println!(
-                    //     "Now analyze function {:?}, {:?}",
-                    //     body.span,
-                    //     self.my_tcx.tcx.def_path_str(def_id)
-                    // );
-                    // only analyze functions defined in current crate
-                    // FIXME: closure?
-                    self.lock_set_facts
-                        .entry(def_id.clone())
-                        .or_insert(FxHashMap::default());
-                    self.visit_body(def_id, body);
-                }
+    /// leave a skipped function's facts empty and its summary unknown, so its callers go on
+    fn give_up(&mut self, def_id: DefId) {
+        if !def_id.is_local() || !self.my_tcx.tcx.is_mir_available(def_id) {
+            return;
+        }
+        self.my_tcx.lock_set_facts.insert(def_id, FxHashMap::default());
+        let summary = FnSummary {
+            def_path: self.my_tcx.tcx.def_path_str(def_id),
+            unknown: true,
+            ..Default::default()
+        };
+        self.my_tcx
+            .summaries
+            .insert(self.my_tcx.tcx, def_id, summary);
+    }
+
+    fn intra_procedural_analysis(&mut self, def_id: DefId) {
+        if self.my_tcx.tcx.is_mir_available(def_id) {
+            // each function is analyzed only once
+            let body = self.my_tcx.tcx.optimized_mir(def_id);
+            if def_id.is_local() && self.my_tcx.lock_set_facts.get(&def_id) == None {
+                // only analyze functions defined in current crate
+                self.my_tcx
+                    .lock_set_facts
+                    .entry(def_id.clone())
+                    .or_insert(FxHashMap::default());
+                self.visit_body(def_id, body);
             }
         }
     }
 
     fn visit_body(&mut self, def_id: DefId, body: &Body<'tcx>) {
         self.init_func(&def_id, body);
+        if self.my_tcx.options.alias_mode == AliasMode::Demand {
+            self.my_tcx.alias_graph.clear_queries();
+        }
+        if self.my_tcx.options.path_sensitive {
+            self.stable_locals = match self.my_tcx.prepass.get(&def_id) {
+                Some(prepass) => prepass.stable_locals.clone(),
+                None => stable_locals(body),
+            };
+            self.path_conditions.clear();
+        }
         // FIXME: redundant clone
         for current_bb_index in self.my_tcx.control_flow_graph[&def_id].clone() {
             // println!("bb {:?} now under lock analysis ", current_bb_index);
@@ -135,14 +214,15 @@ impl<'a, 'tcx> LockSetAnalysis<'a, 'tcx> {
         }
     }
     fn init_func(&mut self, def_id: &DefId, body: &Body) {
-        let lock_set_facts = self.lock_set_facts.get_mut(def_id).unwrap();
+        let lock_set_facts = self.my_tcx.lock_set_facts.get_mut(def_id).unwrap();
         for bb_index in self.my_tcx.control_flow_graph.get(def_id).unwrap().clone() {
             lock_set_facts.entry(bb_index.as_usize()).or_insert(vec![]);
         }
     }
 
     fn visit_bb(&mut self, def_id: DefId, bb_index: usize, body: &Body<'tcx>) {
-        // merge the pres
+        let mut pre_lock_sets = vec![];
+        let mut path: Option<Vec<Condition>> = None;
         for pre in body
             .basic_blocks
             .predecessors()
@@ -150,35 +230,200 @@ impl<'a, 'tcx> LockSetAnalysis<'a, 'tcx> {
             .unwrap()
         {
             // refactor the lock_set_facts access
-            self.merge(pre, def_id, bb_index);
+            let mut failed: Vec<(DefId, Location)> =
+                self.failed_try_acquire(def_id, *pre, bb_index, body).into_iter().collect();
+            let edge = self.edge_path(*pre, bb_index, body);
+            let unwound = self.unwound_locks(def_id, *pre, bb_index, body);
+            if let Some(unwound) = unwound.as_ref() {
+                // a call unwinding acquires nothing
+                failed.push((def_id, body.terminator_loc(*pre)));
+            }
+            let mut unwound = unwound.unwrap_or_default();
+            unwound.extend(self.unflagged_locks(def_id, *pre, bb_index, body));
+            pre_lock_sets.push(self.pre_lock_set(pre, def_id, &failed, edge.as_deref(), &unwound));
+            if let Some(edge) = edge {
+                path = Some(match path {
+                    Some(path) => intersect(&path, &edge),
+                    None => edge,
+                });
+            }
+        }
+        // merge the pres, a lock is held if it is held on any of them
+        *self
+            .my_tcx
+            .lock_set_facts
+            .get_mut(&def_id)
+            .unwrap()
+            .get_mut(&bb_index)
+            .unwrap() = join(&pre_lock_sets);
+        if self.my_tcx.options.path_sensitive {
+            self.path_conditions.insert(bb_index, path.unwrap_or_default());
         }
         let data = &body.basic_blocks[BasicBlock::from(bb_index)];
         // process the terminator
         self.visit_terminator(&def_id, bb_index, &data.terminator().kind, body);
     }
 
-    pub fn merge(&mut self, pre: &BasicBlock, def_id: DefId, bb_index: usize) {
-        // merge the lock set
-        let pre_lock_fact = self.lock_set_facts[&def_id][&pre.as_usize()].clone();
-        self.lock_set_facts
-            .get_mut(&def_id)
-            .unwrap()
-            .get_mut(&bb_index)
-            .unwrap()
-            .clear();
-        self.lock_set_facts
-            .get_mut(&def_id)
-            .unwrap()
-            .get_mut(&bb_index)
-            .unwrap()
-            .meet(&pre_lock_fact);
+    /// the lock set of a pre, without the locks of the acquisitions that failed on the
+    /// way, a try-acquire or a call unwinding, nor the locks the unwinding call drops,
+    /// and in the path-sensitive mode, without the facts whose sites the edge excludes
+    fn pre_lock_set(
+        &self,
+        pre: &BasicBlock,
+        def_id: DefId,
+        failed: &[(DefId, Location)],
+        edge: Option<&[Condition]>,
+        unwound: &[Lock],
+    ) -> LockSummary {
+        let mut pre_lock_fact = self.my_tcx.lock_set_facts[&def_id][&pre.as_usize()].clone();
+        if !failed.is_empty() || !unwound.is_empty() {
+            for lock_set_fact in pre_lock_fact.iter_mut() {
+                lock_set_fact.retain(|fact| {
+                    !(fact.is_acquisition
+                        && (failed.contains(&fact.s_location) || unwound.contains(&fact.lock)))
+                });
+            }
+        }
+        if let Some(edge) = edge {
+            let path_conditions = &self.path_conditions;
+            for lock_set_fact in pre_lock_fact.iter_mut() {
+                lock_set_fact.retain(|fact| {
+                    path_conditions
+                        .get(&fact.s_location.1.block.as_usize())
+                        .map_or(true, |site| !contradicts(site, edge))
+                });
+            }
+        }
+        pre_lock_fact
+    }
+
+    /// the conditions holding on every path through `pre -> bb`,
+    /// `None` in the path-insensitive mode or if `pre` is not visited yet
+    fn edge_path(
+        &self,
+        pre: BasicBlock,
+        bb_index: usize,
+        body: &Body<'tcx>,
+    ) -> Option<Vec<Condition>> {
+        if !self.my_tcx.options.path_sensitive {
+            return None;
+        }
+        let mut conditions = self.path_conditions.get(&pre.as_usize())?.clone();
+        let bb = BasicBlock::from_usize(bb_index);
+        for condition in edge_conditions(self.my_tcx.tcx, body, &self.stable_locals, pre, bb) {
+            if conditions.len() < MAX_PATH_CONDITIONS && !conditions.contains(&condition) {
+                conditions.push(condition);
+            }
+        }
+        Some(conditions)
+    }
+
+    /// if the bb is where the call ending `pre` unwinds to, the locks guarded by the values
+    /// moved into the call; the callee owns them and drops them while unwinding, e.g. the
+    /// guard in the `Err` of a `lock()` is dropped by the `unwrap()` panicking on it
+    fn unwound_locks(
+        &mut self,
+        def_id: DefId,
+        pre: BasicBlock,
+        bb_index: usize,
+        body: &Body<'tcx>,
+    ) -> Option<Vec<Lock>> {
+        let TerminatorKind::Call { args, unwind, .. } = &body.basic_blocks[pre].terminator().kind
+        else {
+            return None;
+        };
+        if *unwind != mir::UnwindAction::Cleanup(BasicBlock::from_usize(bb_index)) {
+            return None;
+        }
+        let mut unwound = vec![];
+        for arg in args.iter() {
+            let Operand::Move(place) = &arg.node else {
+                continue;
+            };
+            for lock in self.guarded_locks(def_id, place) {
+                if !unwound.contains(&lock) {
+                    unwound.push(lock);
+                }
+            }
+        }
+        Some(unwound)
+    }
+
+    /// if the bb is where a drop flag skips a drop while unwinding, the locks guarded by the
+    /// dropped place; the flag is unset as the guard is moved out, e.g. into `drop()`,
+    /// so the place guards nothing when the drop is skipped
+    fn unflagged_locks(
+        &mut self,
+        def_id: DefId,
+        pre: BasicBlock,
+        bb_index: usize,
+        body: &Body<'tcx>,
+    ) -> Vec<Lock> {
+        let data = &body.basic_blocks[pre];
+        let TerminatorKind::SwitchInt { discr, targets } = &data.terminator().kind else {
+            return vec![];
+        };
+        if !data.is_cleanup
+            || !discr.ty(body, self.my_tcx.tcx).is_bool()
+            || targets.target_for_value(0).as_usize() != bb_index
+            || targets.otherwise().as_usize() == bb_index
+        {
+            return vec![];
+        }
+        match &body.basic_blocks[targets.otherwise()].terminator().kind {
+            TerminatorKind::Drop { place, .. } => self.guarded_locks(def_id, place),
+            _ => vec![],
+        }
+    }
+
+    /// the locks guarded by the guards a place holds
+    fn guarded_locks(&mut self, def_id: DefId, place: &Place<'tcx>) -> Vec<Lock> {
+        let node = self.my_tcx.alias_graph.resolve_project(&def_id, place);
+        self.my_tcx
+            .alias_graph
+            .find_guards(node, MAX_LOCK_PATH_LEN)
+            .into_iter()
+            .flat_map(|(_, lock)| locks_of(&self.my_tcx.alias_graph, lock))
+            .collect()
+    }
+
+    /// the try-acquire whose result is matched at the end of `pre`,
+    /// if the bb is only reached when it fails
+    fn failed_try_acquire(
+        &self,
+        def_id: DefId,
+        pre: BasicBlock,
+        bb_index: usize,
+        body: &Body<'tcx>,
+    ) -> Option<(DefId, Location)> {
+        let data = &body.basic_blocks[pre];
+        let TerminatorKind::SwitchInt { discr, targets } = &data.terminator().kind else {
+            return None;
+        };
+        let discr = discr.place()?;
+        // _d = discriminant(_r); switchInt(move _d)
+        let scrutinee = data.statements.iter().rev().find_map(|statement| {
+            match &statement.kind {
+                mir::StatementKind::Assign(assign) if assign.0 == discr => match &assign.1 {
+                    Rvalue::Discriminant(place) => Some(*place),
+                    _ => None,
+                },
+                _ => None,
+            }
+        })?;
+        if !scrutinee.projection.is_empty() {
+            return None;
+        }
+        let (location, success) = self.try_acquires.get(&(def_id, scrutinee.local))?;
+        (targets.target_for_value(*success).as_usize() != bb_index)
+            .then_some((def_id, *location))
     }
 
     fn visit_terminator(
         &mut self,
         def_id: &DefId,
         bb_index: usize,
-        terminator_kind: &TerminatorKind,
+        terminator_kind: &TerminatorKind<'tcx>,
         body: &Body<'tcx>,
     ) {
         match terminator_kind {
@@ -201,82 +446,91 @@ impl<'a, 'tcx> LockSetAnalysis<'a, 'tcx> {
                                 if let DefPathData::ValueNs(name) =
                                     &def_path.data[def_path.data.len() - 1].data
                                 {
-                                    if is_mutex_method(&def_path_str) {
+                                    if let Some(op) = raw_lock_op(&def_path_str) {
+                                        self.visit_raw_lock_op(def_id, bb_index, op, args, body);
+                                    } else if lock_api_acquire(&def_path_str).is_some() {
+                                        let guard = self
+                                            .my_tcx
+                                            .alias_graph
+                                            .resolve_project(def_id, destination);
+                                        if let Some(lock) =
+                                            unsafe { (*guard).get_out_vertex(&EdgeLabel::Guard) }
+                                        {
+                                            self.acquire_locks(def_id, bb_index, lock, body, true);
+                                        }
+                                    } else if is_try_lock_method(&def_path_str) {
+                                        self.visit_try_lock(def_id, bb_index, destination, body);
+                                    } else if is_mutex_method(&def_path_str) {
                                         if name.as_str() == "lock" {
-                                            assert_eq!(1, args.len());
-                                            match &args[0].node {
-                                                // must be move _*
-                                                mir::Operand::Constant(_) => todo!(),
-                                                mir::Operand::Copy(p) | mir::Operand::Move(p) => {
-                                                    let guard = self
-                                                        .my_tcx
-                                                        .alias_graph
-                                                        .resolve_project(def_id, destination);
-                                                    let mut new_lock_set_fact =
-                                                        FxHashSet::default();
-                                                    unsafe {
-                                                        for lock_node in (*(*(*guard)
-                                                            .get_out_vertex(&EdgeLabel::Guard)
-                                                            .unwrap())
-                                                        .alias_set)
-                                                            .iter()
-                                                        {
-                                                            let new_lock = Lock::new(
-                                                                (**lock_node).id.def_id.clone(),
-                                                                (**lock_node).id.index,
-                                                            );
-                                                            for lock_set_fact in self
-                                                                .lock_set_facts
-                                                                .get_mut(def_id)
-                                                                .unwrap()
-                                                                .entry(bb_index)
-                                                                .or_insert(vec![])
-                                                                .iter_mut()
-                                                            {
-                                                                for old_lock_fact in
-                                                                    lock_set_fact.iter()
-                                                                {
-                                                                    if old_lock_fact.is_acquisition
-                                                                        == true
-                                                                        && old_lock_fact.state
-                                                                            == false
-                                                                    {
-                                                                        let old_lock =
-                                                                            old_lock_fact
-                                                                                .lock
-                                                                                .clone();
-                                                                        self.lock_graph.add_edge(
-                                                                            old_lock,
-                                                                            new_lock.clone(),
-                                                                        );
-                                                                    }
-                                                                }
-                                                            }
-                                                            let new_lock_fact = LockFact {
-                                                                is_acquisition: true,
-                                                                state: false,
-                                                                s_location: (
-                                                                    def_id.clone(),
-                                                                    body.terminator_loc(
-                                                                        BasicBlock::from_usize(
-                                                                            bb_index,
-                                                                        ),
-                                                                    ),
-                                                                ),
-                                                                lock: new_lock.clone(),
-                                                            };
-                                                            new_lock_set_fact.insert(new_lock_fact);
-                                                        }
-                                                    }
-                                                    self.lock_set_facts
-                                                        .get_mut(def_id)
-                                                        .unwrap()
-                                                        .get_mut(&bb_index)
-                                                        .unwrap()
-                                                        .push(new_lock_set_fact);
+                                            // the alias analysis links the guard to its lock,
+                                            // unless it could not tell the mutex
+                                            let guard = self
+                                                .my_tcx
+                                                .alias_graph
+                                                .resolve_project(def_id, destination);
+                                            if let Some(lock) =
+                                                unsafe { (*guard).get_out_vertex(&EdgeLabel::Guard) }
+                                            {
+                                                self.acquire_locks(
+                                                    def_id, bb_index, lock, body, true,
+                                                );
+                                            }
+                                        }
+                                    } else if is_future_poll(&def_path_str) {
+                                        // an async lock is acquired when its future is ready,
+                                        // Poll::Ready(guard)
+                                        let ready = self
+                                            .my_tcx
+                                            .alias_graph
+                                            .resolve_project(def_id, destination);
+                                        unsafe {
+                                            if let Some(guard) =
+                                                (*ready).get_out_vertex(&EdgeLabel::new_field(0))
+                                            {
+                                                if let Some(lock) =
+                                                    (*guard).get_out_vertex(&EdgeLabel::Guard)
+                                                {
+                                                    self.acquire_locks(
+                                                        def_id, bb_index, lock, body, true,
+                                                    );
                                                 }
                                             }
                                         }
+                                    } else if let Some(mutable) = refcell_borrow(&def_path_str) {
+                                        self.visit_cell_borrow(
+                                            def_id,
+                                            bb_index,
+                                            mutable,
+                                            destination,
+                                            body,
+                                        );
+                                    } else if let Some(kind) = condvar_op(&def_path_str) {
+                                        self.visit_condvar_op(def_id, bb_index, kind, args, body);
+                                    } else if let Some(op) = channel_op(&def_path_str) {
+                                        self.visit_channel_op(
+                                            def_id,
+                                            bb_index,
+                                            op,
+                                            args,
+                                            destination,
+                                        );
+                                    } else if is_mem_drop(&def_path_str) {
+                                        // `drop(guard)` the inliner left as a call
+                                        if let Some(Operand::Move(dropped)) =
+                                            args.first().map(|arg| &arg.node)
+                                        {
+                                            self.drop_guards(def_id, bb_index, dropped, body);
+                                        }
+                                    } else if fn_id != def_id {
+                                        self.acquire_returned_locks(
+                                            def_id,
+                                            bb_index,
+                                            *fn_id,
+                                            args,
+                                            destination,
+                                            body,
+                                        );
+                                        self.import_raw_ops(def_id, bb_index, *fn_id, args, body);
                                     }
                                 }
                             }
@@ -290,74 +544,1174 @@ impl<'a, 'tcx> LockSetAnalysis<'a, 'tcx> {
                 }
             }
             rustc_middle::mir::TerminatorKind::Drop { place, .. } => {
-                let dropped = self.my_tcx.alias_graph.resolve_project(def_id, place);
-                unsafe {
-                    if let Some(lock) = (*dropped).get_out_vertex(&EdgeLabel::Guard) {
-                        let alias_locks = (*lock).get_alias_set();
-                        if (*alias_locks).len() == 1 {
-                            // if the variable points to more than one locks, skip it
-                            let lock_id = (**(*alias_locks).iter().next().unwrap()).id.clone();
-                            let lock = Lock::new(lock_id.def_id, lock_id.index);
-                            let mut flag = false;
-                            let mut new_lock_set_fact = FxHashSet::default();
-                            for lock_fact_set in self
-                                .lock_set_facts
-                                .get_mut(def_id)
-                                .unwrap()
-                                .get_mut(&bb_index)
-                                .unwrap()
-                                .iter_mut()
-                            {
-                                for lock_fact in lock_fact_set.clone().into_iter() {
-                                    if lock_fact.is_acquisition == true
-                                        && lock_fact.lock == lock
-                                        && lock_fact.state == false
-                                    {
-                                        flag = true;
-                                        if let Some(mut u) = lock_fact_set.take(&lock_fact) {
-                                            u.state = true;
-                                            lock_fact_set.insert(u);
-                                        }
-                                        let new_fact = LockFact {
-                                            is_acquisition: false,
-                                            state: true,
-                                            s_location: (
-                                                def_id.clone(),
-                                                body.terminator_loc(BasicBlock::from_usize(
-                                                    bb_index,
-                                                )),
-                                            ),
-                                            lock: lock.clone(),
-                                        };
-                                        new_lock_set_fact.insert(new_fact);
-                                    }
-                                }
-                            }
-                            if !flag {
-                                let new_fact = LockFact {
-                                    is_acquisition: false,
-                                    state: false,
-                                    s_location: (
-                                        def_id.clone(),
-                                        body.terminator_loc(BasicBlock::from_usize(bb_index)),
-                                    ),
-                                    lock: lock.clone(),
-                                };
-                                new_lock_set_fact.insert(new_fact);
+                self.drop_guards(def_id, bb_index, place, body);
+            }
+            _ => {}
+        }
+    }
+
+    /// dropping a value drops the guards in its fields too
+    fn drop_guards(
+        &mut self,
+        def_id: &DefId,
+        bb_index: usize,
+        place: &Place<'tcx>,
+        body: &Body<'tcx>,
+    ) {
+        let dropped = self.my_tcx.alias_graph.resolve_project(def_id, place);
+        let mut guarded = vec![];
+        for (_, lock) in self.my_tcx.alias_graph.find_guards(dropped, MAX_LOCK_PATH_LEN) {
+            if !guarded.contains(&lock) {
+                guarded.push(lock);
+            }
+        }
+        for lock in guarded {
+            // a guard whose lock was force-unlocked releases it once more
+            if let [released] = &locks_of(&self.my_tcx.alias_graph, lock)[..] {
+                let mut unlock = self.raw_unlock(def_id, bb_index, released, body);
+                let unlocks = &self.my_tcx.raw_unlocks;
+                unlock
+                    .released
+                    .retain(|site| unlocks.iter().any(|other| other.site == *site));
+                if unlock.is_double() {
+                    self.my_tcx.raw_unlocks.push(unlock);
+                }
+            }
+            self.release_locks(def_id, bb_index, lock, body);
+        }
+    }
+
+    /// a dropped guard releases its lock
+    fn release_locks(
+        &mut self,
+        def_id: &DefId,
+        bb_index: usize,
+        lock: *mut AliasGraphNode,
+        body: &Body<'tcx>,
+    ) {
+        let alias_locks = locks_of(&self.my_tcx.alias_graph, lock);
+        if alias_locks.len() != 1 || self.my_tcx.alias_graph.is_collapsed(lock) {
+            // if the variable points to more than one locks, skip it
+            return;
+        }
+        let lock = alias_locks[0].clone();
+        let location = body.terminator_loc(BasicBlock::from_usize(bb_index));
+        let mut flag = false;
+        let mut new_lock_set_fact = FxHashSet::default();
+        for lock_fact_set in self
+            .my_tcx
+            .lock_set_facts
+            .get_mut(def_id)
+            .unwrap()
+            .get_mut(&bb_index)
+            .unwrap()
+            .iter_mut()
+        {
+            for lock_fact in lock_fact_set.clone().into_iter() {
+                if lock_fact.is_acquisition && lock_fact.lock == lock && !lock_fact.state {
+                    flag = true;
+                    if let Some(mut u) = lock_fact_set.take(&lock_fact) {
+                        u.state = true;
+                        lock_fact_set.insert(u);
+                    }
+                    new_lock_set_fact.insert(LockFact {
+                        is_acquisition: false,
+                        state: true,
+                        s_location: (def_id.clone(), location),
+                        lock: lock.clone(),
+                    });
+                }
+            }
+        }
+        if !flag {
+            new_lock_set_fact.insert(LockFact {
+                is_acquisition: false,
+                state: false,
+                s_location: (def_id.clone(), location),
+                lock: lock.clone(),
+            });
+        }
+        self.my_tcx
+            .lock_set_facts
+            .get_mut(def_id)
+            .unwrap()
+            .get_mut(&bb_index)
+            .unwrap()
+            .push(new_lock_set_fact);
+    }
+
+    /// a raw lock is held until it is unlocked explicitly, and so is the lock of a leaked guard
+    fn visit_raw_lock_op(
+        &mut self,
+        def_id: &DefId,
+        bb_index: usize,
+        op: RawLockOp,
+        args: &[Spanned<Operand<'tcx>>],
+        body: &Body<'tcx>,
+    ) {
+        let Some(arg) = args.first().and_then(|arg| arg.node.place()) else {
+            return;
+        };
+        let arg = self.my_tcx.alias_graph.resolve_project(def_id, &arg);
+        let location = body.terminator_loc(BasicBlock::from_usize(bb_index));
+        match op {
+            RawLockOp::Lock => {
+                let lock = self
+                    .my_tcx
+                    .alias_graph
+                    .get_or_create_target(def_id, arg, EdgeLabel::Deref);
+                self.raw_sites.insert((def_id.clone(), location));
+                self.acquire_locks(def_id, bb_index, lock, body, true);
+            }
+            RawLockOp::Unlock => {
+                let lock = self
+                    .my_tcx
+                    .alias_graph
+                    .get_or_create_target(def_id, arg, EdgeLabel::Deref);
+                self.unlock(def_id, bb_index, lock, body);
+            }
+            RawLockOp::Leak => {
+                let leaked: Vec<Lock> = self
+                    .my_tcx
+                    .alias_graph
+                    .find_guards(arg, MAX_LOCK_PATH_LEN)
+                    .into_iter()
+                    .flat_map(|(_, lock)| locks_of(&self.my_tcx.alias_graph, lock))
+                    .collect();
+                let sites: Vec<_> = self.my_tcx.lock_set_facts[def_id][&bb_index]
+                    .iter()
+                    .flatten()
+                    .filter(|fact| {
+                        fact.is_acquisition && !fact.state && leaked.contains(&fact.lock)
+                    })
+                    .map(|fact| fact.s_location)
+                    .collect();
+                self.raw_sites.extend(sites);
+            }
+            RawLockOp::Raw => (),
+        }
+    }
+
+    /// an explicit release, recorded to check that the lock is held
+    fn unlock(
+        &mut self,
+        def_id: &DefId,
+        bb_index: usize,
+        lock: *mut AliasGraphNode,
+        body: &Body<'tcx>,
+    ) {
+        if let [released] = &locks_of(&self.my_tcx.alias_graph, lock)[..] {
+            let unlock = self.raw_unlock(def_id, bb_index, released, body);
+            self.my_tcx.raw_unlocks.push(unlock);
+        }
+        self.release_locks(def_id, bb_index, lock, body);
+    }
+
+    /// how a release at the terminator of a bb meets the earlier acquisitions and releases
+    fn raw_unlock(
+        &self,
+        def_id: &DefId,
+        bb_index: usize,
+        lock: &Lock,
+        body: &Body<'tcx>,
+    ) -> RawUnlock {
+        let mut unlock = RawUnlock {
+            site: (
+                def_id.clone(),
+                body.terminator_loc(BasicBlock::from_usize(bb_index)),
+            ),
+            lock: lock.clone(),
+            held: false,
+            released: vec![],
+        };
+        for fact in self.my_tcx.lock_set_facts[def_id][&bb_index].iter().flatten() {
+            if fact.lock != *lock {
+                continue;
+            }
+            if fact.is_acquisition {
+                unlock.held |= !fact.state;
+            } else if fact.state && !unlock.released.contains(&fact.s_location) {
+                unlock.released.push(fact.s_location);
+            }
+        }
+        unlock
+    }
+
+    /// every member of the lock's alias set is acquired while the held locks are held;
+    /// an acquisition that cannot block forever is not ordered after them
+    fn acquire_locks(
+        &mut self,
+        def_id: &DefId,
+        bb_index: usize,
+        lock: *mut AliasGraphNode,
+        body: &Body<'tcx>,
+        blocking: bool,
+    ) {
+        let location = body.terminator_loc(BasicBlock::from_usize(bb_index));
+        let held = if blocking {
+            self.my_tcx.held_locks(def_id, bb_index)
+        } else {
+            vec![]
+        };
+        let mut new_lock_set_fact = FxHashSet::default();
+        let may_alias = self.my_tcx.alias_graph.is_collapsed(lock);
+        for new_lock in locks_of(&self.my_tcx.alias_graph, lock) {
+            if may_alias {
+                self.my_tcx.lock_graph.add_may_alias(new_lock.clone());
+            }
+            for old_lock in held.iter() {
+                add_lock_order(
+                    &mut self.my_tcx.lock_graph,
+                    &mut self.lock_orders,
+                    def_id,
+                    old_lock.clone(),
+                    new_lock.clone(),
+                );
+            }
+            new_lock_set_fact.insert(LockFact {
+                is_acquisition: true,
+                state: false,
+                s_location: (def_id.clone(), location),
+                lock: new_lock,
+            });
+        }
+        self.my_tcx
+            .lock_set_facts
+            .get_mut(def_id)
+            .unwrap()
+            .get_mut(&bb_index)
+            .unwrap()
+            .push(new_lock_set_fact);
+    }
+
+    /// a try-acquire holds the lock on its success path only, remember its result
+    /// to drop the lock on the paths where it failed
+    fn visit_try_lock(
+        &mut self,
+        def_id: &DefId,
+        bb_index: usize,
+        destination: &Place<'tcx>,
+        body: &Body<'tcx>,
+    ) {
+        let tcx = self.my_tcx.tcx;
+        let result = self.my_tcx.alias_graph.resolve_project(def_id, destination);
+        let Some(lock) = (unsafe { (*result).get_out_vertex(&EdgeLabel::Guard) }) else {
+            return;
+        };
+        let location = body.terminator_loc(BasicBlock::from_usize(bb_index));
+        self.try_sites.insert((def_id.clone(), location));
+        if destination.projection.is_empty() {
+            if let Some(success) = success_discriminant(tcx, destination.ty(body, tcx).ty) {
+                self.try_acquires
+                    .insert((def_id.clone(), destination.local), (location, success));
+            }
+        }
+        self.acquire_locks(def_id, bb_index, lock, body, false);
+    }
+
+    /// a `RefCell` borrow is acquired like a lock, and remembered with the borrows alive
+    /// at it to find the ones that panic
+    fn visit_cell_borrow(
+        &mut self,
+        def_id: &DefId,
+        bb_index: usize,
+        mutable: bool,
+        destination: &Place<'tcx>,
+        body: &Body<'tcx>,
+    ) {
+        let location = body.terminator_loc(BasicBlock::from_usize(bb_index));
+        let guard = self.my_tcx.alias_graph.resolve_project(def_id, destination);
+        let Some(cell) = (unsafe { (*guard).get_out_vertex(&EdgeLabel::Guard) }) else {
+            return;
+        };
+        let held = self.my_tcx.held_borrows(def_id, bb_index);
+        self.my_tcx.borrow_ops.push(BorrowOp {
+            mutable,
+            site: (def_id.clone(), location),
+            owner: def_id.clone(),
+            via: None,
+            cell: locks_of(&self.my_tcx.alias_graph, cell),
+            held,
+        });
+        self.my_tcx
+            .borrow_sites
+            .insert((def_id.clone(), location), mutable);
+        self.acquire_locks(def_id, bb_index, cell, body, false);
+    }
+
+    /// copy the cell borrows of a callee into the caller's terms,
+    /// with the borrows the caller holds at the call
+    fn import_borrow_ops(
+        &mut self,
+        def_id: &DefId,
+        bb_index: usize,
+        callee: DefId,
+        args: &[Spanned<Operand<'tcx>>],
+        body: &Body<'tcx>,
+    ) {
+        let arg_count = self.my_tcx.tcx.optimized_mir(callee).arg_count;
+        let location = body.terminator_loc(BasicBlock::from_usize(bb_index));
+        let held = self.my_tcx.held_borrows(def_id, bb_index);
+        let ops: Vec<BorrowOp> = self
+            .my_tcx
+            .borrow_ops
+            .iter()
+            .filter(|op| op.owner == callee)
+            .cloned()
+            .collect();
+        for op in ops {
+            let mut cell = vec![];
+            for lock in op.cell.iter() {
+                let Some(path) = self.lock_path(callee, arg_count, lock) else {
+                    continue;
+                };
+                for lock in self.resolve_lock_path(def_id, args, &path) {
+                    if !cell.contains(&lock) {
+                        cell.push(lock);
+                    }
+                }
+            }
+            if cell.is_empty() {
+                continue;
+            }
+            self.my_tcx.borrow_ops.push(BorrowOp {
+                owner: def_id.clone(),
+                via: Some((def_id.clone(), location)),
+                cell,
+                held: held.clone(),
+                ..op
+            });
+        }
+    }
+
+    /// a blocking channel operation waits for the channel while holding the locks,
+    /// and the channel's progress depends on every lock acquired before the operation
+    /// that unblocks the other side
+    fn visit_channel_op(
+        &mut self,
+        def_id: &DefId,
+        bb_index: usize,
+        op: ChannelOp,
+        args: &[Spanned<Operand<'tcx>>],
+        destination: &Place<'tcx>,
+    ) {
+        let (blocking, progress, channels) = match op {
+            ChannelOp::Create { bounded } => {
+                let pair_channel = [EdgeLabel::new_field(0), EdgeLabel::Deref];
+                for channel in self.resolve_projection(def_id, destination, &pair_channel) {
+                    self.my_tcx.lock_graph.add_channel(channel, bounded);
+                }
+                return;
+            }
+            ChannelOp::Send { blocking } => {
+                let channels = self.channels_of(def_id, args);
+                // whether a crossbeam channel is bounded is only known where it is created
+                let blocking = blocking.unwrap_or_else(|| {
+                    channels
+                        .iter()
+                        .any(|channel| self.my_tcx.lock_graph.is_bounded(channel))
+                });
+                (blocking, true, channels)
+            }
+            ChannelOp::Recv => {
+                let channels = self.channels_of(def_id, args);
+                // a receive only unblocks the senders of a bounded channel
+                let progress = channels
+                    .iter()
+                    .any(|channel| self.my_tcx.lock_graph.is_bounded(channel));
+                (true, progress, channels)
+            }
+        };
+        for channel in channels.iter() {
+            self.my_tcx.lock_graph.add_channel(channel.clone(), false);
+            if blocking {
+                for held in self.my_tcx.held_locks(def_id, bb_index) {
+                    add_lock_order(
+                        &mut self.my_tcx.lock_graph,
+                        &mut self.lock_orders,
+                        def_id,
+                        held,
+                        channel.clone(),
+                    );
+                }
+            }
+            if progress {
+                for acquired in self.my_tcx.acquired_locks(def_id, bb_index) {
+                    add_lock_order(
+                        &mut self.my_tcx.lock_graph,
+                        &mut self.lock_orders,
+                        def_id,
+                        channel.clone(),
+                        acquired,
+                    );
+                }
+            }
+        }
+    }
+
+    fn visit_condvar_op(
+        &mut self,
+        def_id: &DefId,
+        bb_index: usize,
+        kind: CondvarOpKind,
+        args: &[Spanned<Operand<'tcx>>],
+        body: &Body<'tcx>,
+    ) {
+        let location = body.terminator_loc(BasicBlock::from_usize(bb_index));
+        let condvar = match args.first().and_then(|arg| arg.node.place()) {
+            Some(condvar_ref) => self.resolve_projection(def_id, &condvar_ref, &[EdgeLabel::Deref]),
+            None => vec![],
+        };
+        let mut mutex = vec![];
+        if kind.is_wait() {
+            if let Some(guard) = args.get(1).and_then(|arg| arg.node.place()) {
+                mutex = self.resolve_projection(def_id, &guard, &[EdgeLabel::Guard]);
+                self.release_and_reacquire(def_id, bb_index, location, &mutex);
+            }
+        }
+        self.my_tcx.condvar_ops.push(CondvarOp {
+            kind,
+            site: (def_id.clone(), location),
+            owner: def_id.clone(),
+            condvar,
+            mutex,
+            origin: None,
+        });
+    }
+
+    /// a wait releases the guard's mutex and acquires it again before returning
+    fn release_and_reacquire(
+        &mut self,
+        def_id: &DefId,
+        bb_index: usize,
+        location: Location,
+        mutex: &[Lock],
+    ) {
+        let held = self.my_tcx.held_locks(def_id, bb_index);
+        let lock_summary = self
+            .my_tcx
+            .lock_set_facts
+            .get_mut(def_id)
+            .unwrap()
+            .get_mut(&bb_index)
+            .unwrap();
+        for lock_set_fact in lock_summary.iter_mut() {
+            for lock_fact in lock_set_fact.clone().into_iter() {
+                if lock_fact.is_acquisition && !lock_fact.state && mutex.contains(&lock_fact.lock)
+                {
+                    lock_set_fact.remove(&lock_fact);
+                    lock_set_fact.insert(LockFact {
+                        state: true,
+                        ..lock_fact
+                    });
+                }
+            }
+        }
+        let mut new_lock_set_fact = FxHashSet::default();
+        for lock in mutex {
+            new_lock_set_fact.insert(LockFact {
+                is_acquisition: false,
+                state: true,
+                s_location: (def_id.clone(), location),
+                lock: lock.clone(),
+            });
+            new_lock_set_fact.insert(LockFact {
+                is_acquisition: true,
+                state: false,
+                s_location: (def_id.clone(), location),
+                lock: lock.clone(),
+            });
+        }
+        lock_summary.push(new_lock_set_fact);
+        // waking up acquires the mutex while the other locks are still held
+        for old_lock in held.iter().filter(|lock| !mutex.contains(lock)) {
+            for lock in mutex {
+                add_lock_order(
+                    &mut self.my_tcx.lock_graph,
+                    &mut self.lock_orders,
+                    def_id,
+                    old_lock.clone(),
+                    lock.clone(),
+                );
+            }
+        }
+    }
+
+    /// copy the condvar operations of a callee or a spawned closure into the caller's terms,
+    /// so the operations on one condvar in different functions can be matched
+    fn import_condvar_ops<F>(&mut self, def_id: &DefId, callee: DefId, mut resolve: F)
+    where
+        F: FnMut(&mut Self, &LockPath) -> Vec<Lock>,
+    {
+        let arg_count = self.my_tcx.tcx.optimized_mir(callee).arg_count;
+        let ops: Vec<(usize, CondvarOp)> = self
+            .my_tcx
+            .condvar_ops
+            .iter()
+            .enumerate()
+            .filter(|(_, op)| op.owner == callee)
+            .map(|(index, op)| (index, op.clone()))
+            .collect();
+        for (index, op) in ops {
+            let mut map = |this: &mut Self, locks: &[Lock]| {
+                let mut mapped = vec![];
+                for lock in locks {
+                    if let Some(path) = this.lock_path(callee, arg_count, lock) {
+                        for lock in resolve(this, &path) {
+                            if !mapped.contains(&lock) {
+                                mapped.push(lock);
                             }
-                            self.lock_set_facts
-                                .get_mut(def_id)
-                                .unwrap()
-                                .get_mut(&bb_index)
-                                .unwrap()
-                                .push(new_lock_set_fact);
                         }
                     }
                 }
+                mapped
+            };
+            let condvar = map(self, &op.condvar);
+            if condvar.is_empty() {
+                continue;
+            }
+            let mutex = map(self, &op.mutex);
+            self.my_tcx.condvar_ops.push(CondvarOp {
+                condvar,
+                mutex,
+                owner: def_id.clone(),
+                origin: Some(op.origin.unwrap_or(index)),
+                ..op
+            });
+        }
+    }
+
+    /// the channel of the endpoint a channel method is called on
+    fn channels_of(&mut self, def_id: &DefId, args: &[Spanned<Operand<'tcx>>]) -> Vec<Lock> {
+        let Some(endpoint_ref) = args.first().and_then(|arg| arg.node.place()) else {
+            return vec![];
+        };
+        self.resolve_projection(
+            def_id,
+            &endpoint_ref,
+            &[EdgeLabel::Deref, EdgeLabel::Deref],
+        )
+    }
+
+    fn inter_procedural_analysis(&mut self, def_id: DefId) {
+        if !self.my_tcx.lock_set_facts.contains_key(&def_id) {
+            return;
+        }
+        let body = self.my_tcx.tcx.optimized_mir(def_id);
+        for (bb, data) in body.basic_blocks.iter_enumerated() {
+            // cleanup blocks are only analyzed in the unwind-aware mode
+            if !self.my_tcx.lock_set_facts[&def_id].contains_key(&bb.as_usize()) {
+                continue;
+            }
+            if let TerminatorKind::Call {
+                func,
+                args,
+                destination,
+                ..
+            } = &data.terminator().kind
+            {
+                if let Some(callee) = callee_def_id(func) {
+                    if is_thread_spawn(&self.my_tcx.tcx.def_path_str(callee)) {
+                        let location = body.terminator_loc(bb);
+                        self.record_spawn(&def_id, location, args, destination, body);
+                    } else if callee != def_id {
+                        self.apply_summary(&def_id, bb.as_usize(), callee, args, body);
+                        if self.my_tcx.lock_set_facts.contains_key(&callee) {
+                            self.import_condvar_ops(&def_id, callee, |this, path| {
+                                this.resolve_lock_path(&def_id, args, path)
+                            });
+                            self.import_borrow_ops(&def_id, bb.as_usize(), callee, args, body);
+                        }
+                    }
+                }
+            }
+        }
+        let summary = self.summarize(def_id, body);
+        self.my_tcx
+            .summaries
+            .insert(self.my_tcx.tcx, def_id, summary);
+    }
+
+    /// instantiate the callee's summary at a call site
+    fn apply_summary(
+        &mut self,
+        def_id: &DefId,
+        bb_index: usize,
+        callee: DefId,
+        args: &[Spanned<Operand<'tcx>>],
+        body: &Body<'tcx>,
+    ) {
+        let tcx = self.my_tcx.tcx;
+        let Some(summary) = self.my_tcx.summaries.get(tcx, callee).cloned() else {
+            return;
+        };
+        if summary.unknown || summary.incomplete {
+            self.my_tcx.incomplete.insert(*def_id);
+        }
+        if summary.unknown {
+            self.unknown_calls += 1;
+            return;
+        }
+        // a guard returned by the call is held at it, but not before it
+        let location = body.terminator_loc(BasicBlock::from_usize(bb_index));
+        let held: Vec<Lock> = self
+            .my_tcx
+            .held_locks(def_id, bb_index)
+            .into_iter()
+            .filter(|lock| !self.is_acquired_at(def_id, bb_index, location, lock))
+            .collect();
+        for path in summary.acquires.iter() {
+            for lock in self.resolve_lock_path(def_id, args, path) {
+                for old_lock in held.iter() {
+                    add_lock_order(
+                        &mut self.my_tcx.lock_graph,
+                        &mut self.lock_orders,
+                        def_id,
+                        old_lock.clone(),
+                        lock.clone(),
+                    );
+                }
+                self.callee_acquires
+                    .entry(def_id.clone())
+                    .or_default()
+                    .insert(lock);
+            }
+        }
+        for (from, to) in summary.orders.iter() {
+            let from_locks = self.resolve_lock_path(def_id, args, from);
+            let to_locks = self.resolve_lock_path(def_id, args, to);
+            for from_lock in from_locks.iter() {
+                for to_lock in to_locks.iter() {
+                    add_lock_order(
+                        &mut self.my_tcx.lock_graph,
+                        &mut self.lock_orders,
+                        def_id,
+                        from_lock.clone(),
+                        to_lock.clone(),
+                    );
+                }
             }
-            _ => {}
         }
     }
 
-    fn inter_procedural_analysis(&mut self) {}
+    /// whether a lock is acquired by the terminator of a bb
+    fn is_acquired_at(
+        &self,
+        def_id: &DefId,
+        bb_index: usize,
+        location: Location,
+        lock: &Lock,
+    ) -> bool {
+        self.my_tcx.lock_set_facts[def_id][&bb_index]
+            .iter()
+            .flatten()
+            .any(|fact| {
+                fact.is_acquisition
+                    && fact.s_location == (def_id.clone(), location)
+                    && fact.lock == *lock
+            })
+    }
+
+    /// a guard returned by a callee keeps its lock held in the caller,
+    /// the guard is found at its path in the call's destination
+    fn acquire_returned_locks(
+        &mut self,
+        def_id: &DefId,
+        bb_index: usize,
+        callee: DefId,
+        args: &[Spanned<Operand<'tcx>>],
+        destination: &Place<'tcx>,
+        body: &Body<'tcx>,
+    ) {
+        let tcx = self.my_tcx.tcx;
+        let Some(summary) = self.my_tcx.summaries.get(tcx, callee).cloned() else {
+            return;
+        };
+        for (projection, path) in summary.returns.iter() {
+            let Some(lock) = self
+                .resolve_lock_path(def_id, args, path)
+                .iter()
+                .find_map(|lock| {
+                    let id = lock.id.clone();
+                    self.my_tcx.alias_graph.get_node(&id)
+                })
+            else {
+                continue;
+            };
+            let mut guard = self.my_tcx.alias_graph.resolve_project(def_id, destination);
+            for label in projection.iter() {
+                guard = self
+                    .my_tcx
+                    .alias_graph
+                    .get_or_create_target(def_id, guard, *label);
+            }
+            unsafe {
+                (*guard).add_target(lock, EdgeLabel::Guard);
+            }
+            // the callee's summary orders the lock after the held ones at the call
+            self.acquire_locks(def_id, bb_index, lock, body, false);
+        }
+    }
+
+    /// a callee unlocking a lock it never acquires releases the caller's, and a raw lock
+    /// the callee leaves held stays held in the caller
+    fn import_raw_ops(
+        &mut self,
+        def_id: &DefId,
+        bb_index: usize,
+        callee: DefId,
+        args: &[Spanned<Operand<'tcx>>],
+        body: &Body<'tcx>,
+    ) {
+        let tcx = self.my_tcx.tcx;
+        let Some(summary) = self.my_tcx.summaries.get(tcx, callee).cloned() else {
+            return;
+        };
+        let location = body.terminator_loc(BasicBlock::from_usize(bb_index));
+        for (path, acquired) in summary
+            .releases
+            .iter()
+            .map(|path| (path, false))
+            .chain(summary.holds.iter().map(|path| (path, true)))
+        {
+            let Some(lock) = self
+                .resolve_lock_path(def_id, args, path)
+                .iter()
+                .find_map(|lock| {
+                    let id = lock.id.clone();
+                    self.my_tcx.alias_graph.get_node(&id)
+                })
+            else {
+                continue;
+            };
+            if acquired {
+                self.raw_sites.insert((def_id.clone(), location));
+                self.acquire_locks(def_id, bb_index, lock, body, false);
+            } else {
+                self.unlock(def_id, bb_index, lock, body);
+            }
+        }
+    }
+
+    /// find the locks a spawned thread may acquire, through the captures of its closure
+    fn record_spawn(
+        &mut self,
+        def_id: &DefId,
+        location: Location,
+        args: &[Spanned<Operand<'tcx>>],
+        destination: &Place<'tcx>,
+        body: &Body<'tcx>,
+    ) {
+        let tcx = self.my_tcx.tcx;
+        // the closure or the future is the last argument of every spawn
+        let Some(arg) = args.last() else {
+            return;
+        };
+        let closure = match arg.node.ty(body, tcx).kind() {
+            ty::Closure(closure, _) | ty::Coroutine(closure, _) => *closure,
+            _ => return,
+        };
+        let Some(place) = arg.node.place() else {
+            return;
+        };
+        let closure_body = tcx.optimized_mir(closure);
+        let env = SpawnEnv::new(tcx, closure_body, body, place);
+        let mut acquires: Vec<(Lock, Option<(DefId, Location)>)> = vec![];
+
+        // the closure's own acquisitions, with their sites
+        let mut sites = vec![];
+        if let Some(facts) = self.my_tcx.lock_set_facts.get(&closure) {
+            for summary in facts.values() {
+                for lock_set_fact in summary {
+                    for lock_fact in lock_set_fact {
+                        if lock_fact.is_acquisition
+                            && !self.my_tcx.borrow_sites.contains_key(&lock_fact.s_location)
+                            && !self.try_sites.contains(&lock_fact.s_location)
+                        {
+                            sites.push((lock_fact.lock.clone(), lock_fact.s_location));
+                        }
+                    }
+                }
+            }
+        }
+        for (lock, site) in sites {
+            let Some(path) = self.lock_path(closure, closure_body.arg_count, &lock) else {
+                continue;
+            };
+            for lock in self.resolve_env_path(def_id, &env, &path) {
+                if !acquires.iter().any(|(acquired, _)| *acquired == lock) {
+                    acquires.push((lock, Some(site)));
+                }
+            }
+        }
+        // and the ones of its callees
+        if let Some(summary) = self.my_tcx.summaries.get(tcx, closure).cloned() {
+            for path in summary.acquires.iter() {
+                for lock in self.resolve_env_path(def_id, &env, path) {
+                    if !acquires.iter().any(|(acquired, _)| *acquired == lock) {
+                        acquires.push((lock, None));
+                    }
+                }
+            }
+        }
+
+        // the thread's lock orders hold in the spawner's terms too,
+        // so the orders of different threads meet in the lock graph
+        if let Some(summary) = self.my_tcx.summaries.get(tcx, closure).cloned() {
+            for (from, to) in summary.orders.iter() {
+                let from_locks = self.resolve_env_path(def_id, &env, from);
+                let to_locks = self.resolve_env_path(def_id, &env, to);
+                for from_lock in from_locks.iter() {
+                    for to_lock in to_locks.iter() {
+                        add_lock_order(
+                            &mut self.my_tcx.lock_graph,
+                            &mut self.lock_orders,
+                            def_id,
+                            from_lock.clone(),
+                            to_lock.clone(),
+                        );
+                    }
+                }
+            }
+        }
+
+        self.import_condvar_ops(def_id, closure, |this, path| {
+            this.resolve_env_path(def_id, &env, path)
+        });
+
+        // make sure the handle has a node to be matched with the joins
+        self.my_tcx
+            .alias_graph
+            .resolve_project(def_id, destination);
+        self.my_tcx.threads.push(ThreadSpawn {
+            site: (def_id.clone(), location),
+            closure,
+            handle: GraphNodeId::local(*def_id, destination.local.as_usize()),
+            acquires,
+        });
+    }
+
+    /// map a lock path of a closure to the locks of the closure's creator
+    fn resolve_env_path(
+        &mut self,
+        def_id: &DefId,
+        env: &SpawnEnv<'tcx>,
+        path: &LockPath,
+    ) -> Vec<Lock> {
+        if path.param != 1 {
+            return vec![];
+        }
+        let Some(projection) = path.projection.strip_prefix(&env.prefix[..]) else {
+            return vec![];
+        };
+        match &env.captures {
+            Captures::Aggregate(place) => self.resolve_projection(def_id, place, projection),
+            Captures::Args(args) => {
+                // the k-th capture of an async fn's future is its k-th argument
+                let Some((EdgeLabel::Field(k), rest)) = projection.split_first() else {
+                    return vec![];
+                };
+                match args.get(*k).and_then(|arg| arg.place()) {
+                    Some(arg) => self.resolve_projection(def_id, &arg, rest),
+                    None => vec![],
+                }
+            }
+        }
+    }
+
+    /// map a callee's lock path to the locks of the caller
+    fn resolve_lock_path(
+        &mut self,
+        def_id: &DefId,
+        args: &[Spanned<Operand<'tcx>>],
+        path: &LockPath,
+    ) -> Vec<Lock> {
+        let Some(arg) = path.param.checked_sub(1).and_then(|i| args.get(i)) else {
+            return vec![];
+        };
+        match &arg.node {
+            Operand::Copy(p) | Operand::Move(p) => {
+                self.resolve_projection(def_id, p, &path.projection)
+            }
+            // a reference to a static, whose path goes on from the static itself
+            Operand::Constant(_) => {
+                let Some(static_def_id) = static_ref(self.my_tcx.tcx, &arg.node) else {
+                    return vec![];
+                };
+                let Some((EdgeLabel::Deref, projection)) = path.projection.split_first() else {
+                    return vec![];
+                };
+                let node = self
+                    .my_tcx
+                    .alias_graph
+                    .get_or_insert_node(GraphNodeId::local(static_def_id, 0));
+                self.resolve_projection_from(def_id, node, projection)
+            }
+        }
+    }
+
+    fn resolve_projection(
+        &mut self,
+        def_id: &DefId,
+        place: &Place<'tcx>,
+        projection: &[EdgeLabel],
+    ) -> Vec<Lock> {
+        let node = self.my_tcx.alias_graph.resolve_project(def_id, place);
+        self.resolve_projection_from(def_id, node, projection)
+    }
+
+    fn resolve_projection_from(
+        &mut self,
+        def_id: &DefId,
+        mut node: *mut AliasGraphNode,
+        projection: &[EdgeLabel],
+    ) -> Vec<Lock> {
+        for label in projection.iter() {
+            node = self
+                .my_tcx
+                .alias_graph
+                .get_or_create_target(def_id, node, *label);
+        }
+        let locks = locks_of(&self.my_tcx.alias_graph, node);
+        if self.my_tcx.alias_graph.is_collapsed(node) {
+            for lock in locks.iter() {
+                self.my_tcx.lock_graph.add_may_alias(lock.clone());
+            }
+        }
+        locks
+    }
+
+    fn summarize(&mut self, def_id: DefId, body: &Body<'tcx>) -> FnSummary {
+        let mut acquired = FxHashSet::default();
+        for summary in self.my_tcx.lock_set_facts[&def_id].values() {
+            for lock_set_fact in summary {
+                for lock_fact in lock_set_fact {
+                    // cell borrows are matched through the borrow operations instead,
+                    // and a try-acquire never makes the caller wait
+                    if lock_fact.is_acquisition
+                        && !self.my_tcx.borrow_sites.contains_key(&lock_fact.s_location)
+                        && !self.try_sites.contains(&lock_fact.s_location)
+                    {
+                        acquired.insert(lock_fact.lock.clone());
+                    }
+                }
+            }
+        }
+        if let Some(locks) = self.callee_acquires.get(&def_id) {
+            acquired.extend(locks.iter().cloned());
+        }
+
+        let mut summary = FnSummary {
+            def_path: self.my_tcx.tcx.def_path_str(def_id),
+            incomplete: self.my_tcx.incomplete.contains(&def_id),
+            ..Default::default()
+        };
+        for lock in acquired.iter() {
+            if let Some(path) = self.lock_path(def_id, body.arg_count, lock) {
+                if !summary.acquires.contains(&path) {
+                    summary.acquires.push(path);
+                }
+            }
+        }
+        // guards in the return value keep their locks held in the caller
+        let mut held_at_return = vec![];
+        for (bb, data) in body.basic_blocks.iter_enumerated() {
+            if matches!(data.terminator().kind, TerminatorKind::Return) {
+                held_at_return.extend(self.my_tcx.held_locks(&def_id, bb.as_usize()));
+            }
+        }
+        // resolved rather than looked up, for the demand-driven mode to give it the
+        // guards flowing into it
+        let ret = self
+            .my_tcx
+            .alias_graph
+            .resolve_project(&def_id, &Place::return_place());
+        for (projection, lock) in self.my_tcx.alias_graph.find_guards(ret, MAX_LOCK_PATH_LEN) {
+            for lock in locks_of(&self.my_tcx.alias_graph, lock) {
+                if !held_at_return.contains(&lock) {
+                    continue;
+                }
+                if let Some(path) = self.lock_path(def_id, body.arg_count, &lock) {
+                    let returned = (projection.clone(), path);
+                    if !summary.returns.contains(&returned) {
+                        summary.returns.push(returned);
+                    }
+                }
+            }
+        }
+        // releases the caller has to match, and raw locks it has to release; the unlock
+        // of a lock acquired on other paths is reported here instead
+        for unlock in self.my_tcx.raw_unlocks.iter() {
+            if unlock.site.0 != def_id || !unlock.is_unmatched() || acquired.contains(&unlock.lock)
+            {
+                continue;
+            }
+            if let Some(path) = self.lock_path(def_id, body.arg_count, &unlock.lock) {
+                if !summary.releases.contains(&path) {
+                    summary.releases.push(path);
+                }
+            }
+        }
+        for (bb, data) in body.basic_blocks.iter_enumerated() {
+            let facts = self.my_tcx.lock_set_facts[&def_id].get(&bb.as_usize());
+            let Some(facts) = facts.filter(|_| {
+                matches!(data.terminator().kind, TerminatorKind::Return)
+            }) else {
+                continue;
+            };
+            for fact in facts.iter().flatten() {
+                if !fact.is_acquisition || fact.state || !self.raw_sites.contains(&fact.s_location)
+                {
+                    continue;
+                }
+                if let Some(path) = self.lock_path(def_id, body.arg_count, &fact.lock) {
+                    if !summary.holds.contains(&path) {
+                        summary.holds.push(path);
+                    }
+                }
+            }
+        }
+        for (from, to) in self.lock_orders.get(&def_id).cloned().unwrap_or_default() {
+            if let (Some(from), Some(to)) = (
+                self.lock_path(def_id, body.arg_count, &from),
+                self.lock_path(def_id, body.arg_count, &to),
+            ) {
+                if !summary.orders.contains(&(from.clone(), to.clone())) {
+                    summary.orders.push((from, to));
+                }
+            }
+        }
+        summary
+    }
+
+    /// express a lock by a path from one of the parameters;
+    /// locks created inside the function are invisible to callers
+    fn lock_path(&self, def_id: DefId, arg_count: usize, lock: &Lock) -> Option<LockPath> {
+        let alias_graph = &self.my_tcx.alias_graph;
+        let target = alias_graph.get_node(&lock.id)?;
+        for param in 1..=arg_count {
+            let Some(root) = alias_graph.get_node(&GraphNodeId::local(def_id, param)) else {
+                continue;
+            };
+            if let Some(projection) = alias_graph.find_path(root, target, MAX_LOCK_PATH_LEN) {
+                return Some(LockPath::new(param, projection));
+            }
+        }
+        None
+    }
+}
+
+/// the longest parameter projection exported in a summary,
+/// long enough for a coroutine reaching a lock through `Pin<&mut Self>` and an `Arc`
+const MAX_LOCK_PATH_LEN: usize = 6;
+
+/// where the captures of a spawned closure or coroutine come from
+struct SpawnEnv<'tcx> {
+    /// the labels from the body's first parameter to its captures
+    prefix: Vec<EdgeLabel>,
+    captures: Captures<'tcx>,
+}
+
+enum Captures<'tcx> {
+    /// the closure or the async block created at a place
+    Aggregate(Place<'tcx>),
+    /// the arguments of the async fn returning the future
+    Args(Vec<Operand<'tcx>>),
+}
+
+impl<'tcx> SpawnEnv<'tcx> {
+    fn new(
+        tcx: TyCtxt<'tcx>,
+        closure_body: &Body<'tcx>,
+        body: &Body<'tcx>,
+        place: Place<'tcx>,
+    ) -> Self {
+        let self_ty = closure_body.local_decls[Local::from_usize(1)].ty;
+        // a closure may take its env by reference, and a coroutine takes `Pin<&mut Self>`
+        let prefix = match self_ty.kind() {
+            ty::Ref(..) => vec![EdgeLabel::Deref],
+            ty::Adt(adt, _) if tcx.lang_items().pin_type() == Some(adt.did()) => {
+                vec![EdgeLabel::new_field(0), EdgeLabel::Deref]
+            }
+            _ => vec![],
+        };
+        let captures = match async_fn_call(tcx, body, place) {
+            Some(args) => Captures::Args(args),
+            None => Captures::Aggregate(place),
+        };
+        Self { prefix, captures }
+    }
+}
+
+/// the arguments of the async fn call a future comes from, following moves
+fn async_fn_call<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    body: &Body<'tcx>,
+    place: Place<'tcx>,
+) -> Option<Vec<Operand<'tcx>>> {
+    if !place.projection.is_empty() {
+        return None;
+    }
+    for data in body.basic_blocks.iter() {
+        for statement in data.statements.iter() {
+            if let mir::StatementKind::Assign(assign) = &statement.kind {
+                if let (lhs, Rvalue::Use(Operand::Move(rhs) | Operand::Copy(rhs))) =
+                    (&assign.0, &assign.1)
+                {
+                    if lhs.local == place.local && lhs.projection.is_empty() {
+                        return async_fn_call(tcx, body, *rhs);
+                    }
+                }
+            }
+        }
+        if let TerminatorKind::Call {
+            func,
+            args,
+            destination,
+            ..
+        } = &data.terminator().kind
+        {
+            if destination.local == place.local && destination.projection.is_empty() {
+                let callee = callee_def_id(func)?;
+                if !tcx.asyncness(callee).is_async() {
+                    return None;
+                }
+                return Some(args.iter().map(|arg| arg.node.clone()).collect());
+            }
+        }
+    }
+    None
+}
+
+fn add_lock_order(
+    lock_graph: &mut LockGraph,
+    lock_orders: &mut FxHashMap<DefId, FxHashSet<(Lock, Lock)>>,
+    def_id: &DefId,
+    from: Lock,
+    to: Lock,
+) {
+    lock_orders
+        .entry(def_id.clone())
+        .or_default()
+        .insert((from.clone(), to.clone()));
+    lock_graph.add_edge(from, to);
+}
+
+/// the locks a node's alias set names, one per location: the members the graph maps
+/// to the same node, e.g. all of a unified set, are one lock, named by its least id
+pub(crate) fn locks_of(alias_graph: &AliasGraph, node: *mut AliasGraphNode) -> Vec<Lock> {
+    let mut locations: FxHashMap<*mut AliasGraphNode, GraphNodeId> = FxHashMap::default();
+    unsafe {
+        for member in (*(*node).get_alias_set()).iter() {
+            let id = &(**member).id;
+            let location = alias_graph.get_node(id).unwrap_or(*member);
+            let name = locations.entry(location).or_insert_with(|| id.clone());
+            if *id < *name {
+                *name = id.clone();
+            }
+        }
+    }
+    let mut locks: Vec<Lock> = locations.into_values().map(Lock::new).collect();
+    locks.sort_by(|a, b| a.id.cmp(&b.id));
+    locks
 }
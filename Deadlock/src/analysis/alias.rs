@@ -1,33 +1,47 @@
-use std::rc::Rc;
+use std::{collections::BTreeMap, rc::Rc};
 
 use graph::AliasGraph;
-use node::{set_local_id, AliasGraphNode, EdgeLabel, GraphNodeId};
+use node::{AliasGraphNode, CallRole, EdgeLabel, GraphNodeId, NodeKey};
 use rustc_hash::{FxHashMap, FxHashSet};
 use rustc_hir::{
     def_id::{DefId, LocalDefId},
     definitions::DefPathData,
 };
+use rustc_index::IndexVec;
 use rustc_middle::{
     mir::{
-        self, BasicBlock, Body, HasLocalDecls, Local, LocalDecls, Place, Rvalue, Statement,
-        TerminatorKind,
+        self, AggregateKind, BasicBlock, BinOp, Body, CastKind, HasLocalDecls, Local, LocalDecls,
+        Place, Rvalue, Statement, TerminatorKind,
     },
-    ty::{Ty, TyCtxt},
+    ty::{self, Ty, TyCtxt},
 };
+use rustc_span::source_map::Spanned;
+use rustc_target::abi::FieldIdx;
 
-use crate::context::MyTcx;
+use crate::{context::MyTcx, option::AliasMode};
 
 use super::{
     callgraph::{call_graph_node::Call, CallGraph},
-    tools::{is_lock, is_mutex_method, is_smart_pointer},
+    isolate::isolate,
+    prepass::bb_order,
+    tools::{
+        channel_op, condvar_op, is_async_lock_method, is_future_poll, is_future_wrapper, is_lock,
+        guard_map, is_mutex_method, is_smart_pointer, is_try_lock_method, lock_api_acquire,
+        raw_lock_op, refcell_borrow, static_ref, ChannelOp, RawLockOp,
+    },
 };
 
 pub mod graph;
 pub mod node;
 
+/// the deepest node of a callee copied for a call site, as deep as a summary's lock paths
+const MAX_COPY_DEPTH: usize = 6;
+
 pub struct AliasAnalysis<'a, 'tcx> {
     my_tcx: &'a mut MyTcx<'tcx>,
     num_iteration: i32,
+    /// the values given the unknown node, by why
+    fallbacks: BTreeMap<&'static str, usize>,
 }
 
 impl<'a, 'tcx> AliasAnalysis<'a, 'tcx> {
@@ -35,6 +49,7 @@ impl<'a, 'tcx> AliasAnalysis<'a, 'tcx> {
         Self {
             my_tcx,
             num_iteration: 1,
+            fallbacks: BTreeMap::new(),
         }
     }
 
@@ -51,28 +66,46 @@ impl<'a, 'tcx> AliasAnalysis<'a, 'tcx> {
         tracing::info!("Start alias analysis");
     }
 
-    fn after_run(&self) {
+    fn after_run(&mut self) {
         tracing::info!("Finish alias analysis");
+        let collapsed = self.my_tcx.alias_graph.collapsed_count();
+        if collapsed > 0 {
+            self.fallbacks.insert("collapsed array", collapsed);
+        }
+        if !self.fallbacks.is_empty() {
+            let counts: Vec<String> = self
+                .fallbacks
+                .iter()
+                .map(|(reason, count)| format!("{} {}(s)", count, reason))
+                .collect();
+            let note = format!("imprecise values from {}", counts.join(", "));
+            self.my_tcx.reporter.note(note);
+        }
     }
 
-    fn init_func(&mut self, def_id: &DefId, body: &Body) {
-        // compute the confrol flow graph in a reverse post-order
-        let mut reverse_post_order = vec![];
-        for bb in body.basic_blocks.reverse_postorder() {
-            if !body.basic_blocks.get(*bb).unwrap().is_cleanup {
-                reverse_post_order.push(bb.clone());
-            }
-        }
+    fn init_func(&mut self, def_id: &DefId, body: &Body<'tcx>) {
+        // the confrol flow graph in a reverse post-order, from the prepass if it ran
+        let reverse_post_order = match self.my_tcx.prepass.get(def_id) {
+            Some(prepass) => prepass.order.clone(),
+            None => bb_order(body, self.my_tcx.options.unwind_aware),
+        };
         self.my_tcx
             .control_flow_graph
             .entry(def_id.clone())
             .or_insert(reverse_post_order);
-        set_local_id(body.local_decls.len());
+        let index_constants = index_constants(self.my_tcx.tcx, body);
+        self.my_tcx
+            .alias_graph
+            .set_index_constants(def_id.clone(), index_constants);
+        let coroutine_fields = coroutine_fields(self.my_tcx.tcx, def_id, body);
+        self.my_tcx
+            .alias_graph
+            .set_coroutine_fields(*def_id, coroutine_fields);
         // create node for each parameter
         for index in 0..body.arg_count {
             self.my_tcx
                 .alias_graph
-                .get_or_insert_node(GraphNodeId::new(def_id.clone(), Some(index + 1)));
+                .get_or_insert_node(GraphNodeId::local(*def_id, index + 1));
         }
     }
 
@@ -89,7 +122,9 @@ impl<'a, 'tcx> AliasAnalysis<'a, 'tcx> {
                     let body = self.my_tcx.tcx.optimized_mir(def_id);
                     // only analyze functions defined in current crate
                     // FIXME: closure?
-                    self.visit_body(def_id, body);
+                    if let Err(reason) = isolate(|| self.visit_body(def_id, body)) {
+                        self.my_tcx.skip(def_id, "alias analysis", reason);
+                    }
                 }
             }
         }
@@ -147,31 +182,128 @@ impl<'a, 'tcx> AliasAnalysis<'a, 'tcx> {
         // resolve rhs
 
         match rhs {
-            Rvalue::Use(op) => match op {
-                mir::Operand::Copy(p) | mir::Operand::Move(p) => {
-                    self.visit_copy_or_move(def_id, lhs, p);
+            Rvalue::Use(op) | Rvalue::ShallowInitBox(op, _) => {
+                self.visit_operand(def_id, lhs, op);
+            }
+            Rvalue::AddressOf(_, p) | Rvalue::Ref(_, _, p) => {
+                self.visit_address_of_or_ref(def_id, lhs, p);
+            }
+            Rvalue::Repeat(op, _) => self.visit_repeat(def_id, lhs, op),
+            Rvalue::ThreadLocalRef(static_def_id) => {
+                let node_x = self.my_tcx.alias_graph.resolve_project(def_id, lhs);
+                self.point_to_static(node_x, *static_def_id);
+            }
+            Rvalue::Cast(kind, op, _) => match kind {
+                // an integer may point anywhere
+                CastKind::PointerWithExposedProvenance => {
+                    self.visit_unknown(def_id, lhs, "integer-to-pointer cast");
                 }
-                mir::Operand::Constant(_) => {
-                    self.visit_constant(def_id, lhs);
+                // the bytes may be read at a type of another shape
+                CastKind::Transmute => {
+                    self.visit_operand(def_id, lhs, op);
+                    self.visit_unknown(def_id, lhs, "transmute");
                 }
+                CastKind::PointerCoercion(_)
+                | CastKind::PtrToPtr
+                | CastKind::FnPtrToPtr
+                | CastKind::DynStar => self.visit_operand(def_id, lhs, op),
+                CastKind::PointerExposeProvenance
+                | CastKind::IntToInt
+                | CastKind::FloatToInt
+                | CastKind::FloatToFloat
+                | CastKind::IntToFloat => self.visit_constant(def_id, lhs),
             },
-            Rvalue::AddressOf(_, p) | Rvalue::Ref(_, _, p) => {
-                self.visit_address_of_or_ref(def_id, lhs, p);
+            // an offset pointer points into the same allocation
+            Rvalue::BinaryOp(BinOp::Offset, operands) => {
+                self.visit_operand(def_id, lhs, &operands.0);
             }
-            Rvalue::Repeat(_, _) => todo!(),
-            Rvalue::ThreadLocalRef(_) => todo!(),
-            Rvalue::Len(_) => todo!(),
-            Rvalue::Cast(_, _, _) => (),
             Rvalue::Discriminant(p) => self.visit_copy_or_move(def_id, lhs, p),
-            Rvalue::Aggregate(_, _) => (), // TODO: 直接创建struct时
-            Rvalue::ShallowInitBox(_, _) => todo!(),
+            Rvalue::Aggregate(kind, operands) => {
+                self.visit_aggregate(def_id, lhs, kind, operands);
+            }
             Rvalue::CopyForDeref(p) => {
                 self.visit_copy_or_move(def_id, lhs, p);
             }
-            _ => (),
+            Rvalue::Len(_)
+            | Rvalue::BinaryOp(_, _)
+            | Rvalue::NullaryOp(_, _)
+            | Rvalue::UnaryOp(_, _) => self.visit_constant(def_id, lhs),
         }
     }
 
+    fn visit_operand(&mut self, def_id: &DefId, lhs: &Place, op: &mir::Operand) {
+        match op {
+            mir::Operand::Copy(p) | mir::Operand::Move(p) => {
+                self.visit_copy_or_move(def_id, lhs, p);
+            }
+            mir::Operand::Constant(_) => match static_ref(self.my_tcx.tcx, op) {
+                Some(static_def_id) => {
+                    let node_x = self.my_tcx.alias_graph.resolve_project(def_id, lhs);
+                    self.point_to_static(node_x, static_def_id);
+                }
+                None => self.visit_constant(def_id, lhs),
+            },
+        }
+    }
+
+    /// a static's node is the return place of its body, the same for every use of it
+    fn point_to_static(&mut self, node: *mut AliasGraphNode, static_def_id: DefId) {
+        let static_node = self
+            .my_tcx
+            .alias_graph
+            .get_or_insert_node(GraphNodeId::local(static_def_id, 0));
+        unsafe {
+            if !(*node).contains_target(static_node, &EdgeLabel::Deref) {
+                (*node).add_target(static_node, EdgeLabel::Deref);
+            }
+        }
+    }
+
+    /// the node a reference operand points to, the static of a `const &STATIC`;
+    /// `None` for other constants
+    fn pointee(&mut self, def_id: &DefId, op: &mir::Operand) -> Option<*mut AliasGraphNode> {
+        match op {
+            mir::Operand::Copy(p) | mir::Operand::Move(p) => {
+                let reference = self.my_tcx.alias_graph.resolve_project(def_id, p);
+                Some(self.my_tcx.alias_graph.get_or_create_target(
+                    def_id,
+                    reference,
+                    EdgeLabel::Deref,
+                ))
+            }
+            mir::Operand::Constant(_) => {
+                let static_def_id = static_ref(self.my_tcx.tcx, op)?;
+                Some(
+                    self.my_tcx
+                        .alias_graph
+                        .get_or_insert_node(GraphNodeId::local(static_def_id, 0)),
+                )
+            }
+        }
+    }
+
+    /// lhs = [op; n]: every element is op
+    fn visit_repeat(&mut self, def_id: &DefId, lhs: &Place, op: &mir::Operand) {
+        let array = self.my_tcx.alias_graph.resolve_project(def_id, lhs);
+        let element = self
+            .my_tcx
+            .alias_graph
+            .get_or_create_target(def_id, array, EdgeLabel::Index);
+        if let Some(p) = op.place() {
+            let node_y = self.my_tcx.alias_graph.resolve_project(def_id, &p);
+            self.flow(node_y, element);
+        }
+    }
+
+    /// lhs holds a value the analysis cannot tell the origin of, which may alias with
+    /// any other such value
+    fn visit_unknown(&mut self, def_id: &DefId, lhs: &Place, reason: &'static str) {
+        let node_x = self.my_tcx.alias_graph.resolve_project(def_id, lhs);
+        let unknown = self.my_tcx.alias_graph.unknown();
+        self.flow(unknown, node_x);
+        *self.fallbacks.entry(reason).or_default() += 1;
+    }
+
     fn visit_constant(&mut self, def_id: &DefId, lhs: &Place) {
         self.my_tcx.alias_graph.resolve_project(def_id, lhs);
     }
@@ -179,7 +311,245 @@ impl<'a, 'tcx> AliasAnalysis<'a, 'tcx> {
     fn visit_copy_or_move(&mut self, def_id: &DefId, lhs: &Place, rhs: &Place) {
         let node_x = self.my_tcx.alias_graph.resolve_project(def_id, lhs);
         let node_y = self.my_tcx.alias_graph.resolve_project(def_id, rhs);
-        self.make_alias(node_x, node_y);
+        self.flow(node_y, node_x);
+    }
+
+    /// lhs = Aggregate { op_0, op_1, .. } is handled as lhs.i = op_i,
+    /// which also links a closure's captures to its env
+    fn visit_aggregate(
+        &mut self,
+        def_id: &DefId,
+        lhs: &Place,
+        kind: &AggregateKind<'tcx>,
+        operands: &IndexVec<FieldIdx, mir::Operand<'tcx>>,
+    ) {
+        let aggregate = self.my_tcx.alias_graph.resolve_project(def_id, lhs);
+        for (index, op) in operands.iter_enumerated() {
+            let label = match kind {
+                AggregateKind::Array(_) => EdgeLabel::Element(index.as_usize()),
+                // a union only initializes its active field
+                AggregateKind::Adt(_, _, _, _, Some(active_field)) => {
+                    EdgeLabel::new_field(active_field.as_usize())
+                }
+                _ => EdgeLabel::new_field(index.as_usize()),
+            };
+            match op {
+                mir::Operand::Copy(p) | mir::Operand::Move(p) => {
+                    let field_node =
+                        self.my_tcx.alias_graph.get_or_create_target(def_id, aggregate, label);
+                    let node_y = self.my_tcx.alias_graph.resolve_project(def_id, p);
+                    self.flow(node_y, field_node);
+                }
+                mir::Operand::Constant(_) => (),
+            }
+        }
+    }
+
+    /// guard = mutex.lock() of parking_lot, or `lock_arc` on an `&Arc<Mutex>`
+    fn visit_lock_api_acquire(
+        &mut self,
+        def_id: &DefId,
+        args: &[Spanned<mir::Operand<'tcx>>],
+        destination: &Place,
+        through_arc: bool,
+    ) {
+        let Some(mut lock) = args.first().and_then(|arg| self.pointee(def_id, &arg.node)) else {
+            return;
+        };
+        let guard = self.my_tcx.alias_graph.resolve_project(def_id, destination);
+        if through_arc {
+            lock = self
+                .my_tcx
+                .alias_graph
+                .get_or_create_target(def_id, lock, EdgeLabel::Deref);
+        }
+        unsafe {
+            (*guard).add_target(lock, EdgeLabel::Guard);
+        }
+    }
+
+    /// raw = mutex.raw() reaches the mutex itself, and a raw lock or unlock
+    /// needs a node for the lock behind `&self`
+    fn visit_raw_lock_op(
+        &mut self,
+        def_id: &DefId,
+        args: &[Spanned<mir::Operand<'tcx>>],
+        destination: &Place,
+        op: RawLockOp,
+    ) {
+        let Some(lock_ref) = args.first().and_then(|arg| arg.node.place()) else {
+            return;
+        };
+        let lock_ref = self.my_tcx.alias_graph.resolve_project(def_id, &lock_ref);
+        match op {
+            RawLockOp::Raw => {
+                let raw = self.my_tcx.alias_graph.resolve_project(def_id, destination);
+                self.flow(lock_ref, raw);
+            }
+            RawLockOp::Lock | RawLockOp::Unlock => {
+                self.my_tcx
+                    .alias_graph
+                    .get_or_create_target(def_id, lock_ref, EdgeLabel::Deref);
+            }
+            // the guard keeps its lock, the lock set analysis marks it leaked
+            RawLockOp::Leak => (),
+        }
+    }
+
+    /// mapped = Guard::map(guard, f), the mapped guard still holds the guard's lock
+    fn visit_guard_map(
+        &mut self,
+        def_id: &DefId,
+        args: &[Spanned<mir::Operand<'tcx>>],
+        destination: &Place,
+        in_result: bool,
+    ) {
+        let Some(guard) = args.first().and_then(|arg| arg.node.place()) else {
+            return;
+        };
+        let guard = self.my_tcx.alias_graph.resolve_project(def_id, &guard);
+        let lock = self
+            .my_tcx
+            .alias_graph
+            .get_or_create_target(def_id, guard, EdgeLabel::Guard);
+        let mapped = self.my_tcx.alias_graph.resolve_project(def_id, destination);
+        unsafe {
+            (*mapped).add_target(lock, EdgeLabel::Guard);
+        }
+        if in_result {
+            // both the `Ok` and the `Err` hold a guard
+            let mapped = self
+                .my_tcx
+                .alias_graph
+                .get_or_create_target(def_id, mapped, EdgeLabel::new_field(0));
+            unsafe {
+                (*mapped).add_target(lock, EdgeLabel::Guard);
+            }
+        }
+    }
+
+    /// result = mutex.try_lock(), the guard is the payload of the `Ok` or `Some`;
+    /// the result guards the lock too, as it is unwrapped by aliasing it
+    fn visit_try_lock(
+        &mut self,
+        def_id: &DefId,
+        args: &[Spanned<mir::Operand<'tcx>>],
+        destination: &Place,
+    ) {
+        let Some(lock) = args.first().and_then(|arg| self.pointee(def_id, &arg.node)) else {
+            return;
+        };
+        let result = self.my_tcx.alias_graph.resolve_project(def_id, destination);
+        let guard = self
+            .my_tcx
+            .alias_graph
+            .get_or_create_target(def_id, result, EdgeLabel::new_field(0));
+        unsafe {
+            (*result).add_target(lock, EdgeLabel::Guard);
+            (*guard).add_target(lock, EdgeLabel::Guard);
+        }
+    }
+
+    /// guard = RefCell::borrow(cell_ref), a `Ref` or `RefMut` guards the cell like a lock
+    fn visit_cell_borrow(
+        &mut self,
+        def_id: &DefId,
+        args: &[Spanned<mir::Operand<'tcx>>],
+        destination: &Place,
+    ) {
+        let Some(cell) = args.first().and_then(|arg| self.pointee(def_id, &arg.node)) else {
+            return;
+        };
+        let guard = self.my_tcx.alias_graph.resolve_project(def_id, destination);
+        unsafe {
+            (*guard).add_target(cell, EdgeLabel::Guard);
+        }
+    }
+
+    /// future = mutex.lock(), the future points to the lock until it is polled
+    fn visit_async_lock(
+        &mut self,
+        def_id: &DefId,
+        args: &[Spanned<mir::Operand<'tcx>>],
+        destination: &Place,
+    ) {
+        let Some(lock) = args.first().and_then(|arg| self.pointee(def_id, &arg.node)) else {
+            return;
+        };
+        let future = self.my_tcx.alias_graph.resolve_project(def_id, destination);
+        unsafe {
+            (*future).add_target(lock, EdgeLabel::Pending);
+        }
+    }
+
+    /// Poll::Ready(guard) = Future::poll(Pin<&mut future>, cx)
+    fn visit_future_poll(
+        &mut self,
+        def_id: &DefId,
+        args: &[Spanned<mir::Operand<'tcx>>],
+        destination: &Place,
+    ) {
+        let Some(pinned) = args.first().and_then(|arg| arg.node.place()) else {
+            return;
+        };
+        let pinned = self.my_tcx.alias_graph.resolve_project(def_id, &pinned);
+        unsafe {
+            let Some(future) = (*pinned).get_out_vertex(&EdgeLabel::Deref) else {
+                return;
+            };
+            let Some(lock) = (*future).get_out_vertex(&EdgeLabel::Pending) else {
+                return;
+            };
+            let ready = self.my_tcx.alias_graph.resolve_project(def_id, destination);
+            let guard = self.my_tcx.alias_graph.get_or_create_target(
+                def_id,
+                ready,
+                EdgeLabel::new_field(0),
+            );
+            if !(*guard).contains_target(lock, &EdgeLabel::Guard) {
+                (*guard).add_target(lock, EdgeLabel::Guard);
+            }
+        }
+    }
+
+    /// guard = condvar.wait(guard), the returned guard is the one passed in
+    fn visit_condvar_wait(
+        &mut self,
+        def_id: &DefId,
+        args: &[Spanned<mir::Operand<'tcx>>],
+        destination: &Place,
+        in_tuple: bool,
+    ) {
+        let Some(guard) = args.get(1).and_then(|arg| arg.node.place()) else {
+            return;
+        };
+        let mut returned = self.my_tcx.alias_graph.resolve_project(def_id, destination);
+        if in_tuple {
+            returned = self.my_tcx.alias_graph.get_or_create_target(
+                def_id,
+                returned,
+                EdgeLabel::new_field(0),
+            );
+        }
+        let guard = self.my_tcx.alias_graph.resolve_project(def_id, &guard);
+        self.flow(guard, returned);
+    }
+
+    /// (sender, receiver) = channel(), both endpoints point to the same channel
+    fn visit_channel_creation(&mut self, def_id: &DefId, bb_index: usize, destination: &Place) {
+        let pair = self.my_tcx.alias_graph.resolve_project(def_id, destination);
+        let key = NodeKey::Call { block: bb_index, role: CallRole::Channel };
+        let channel = self.my_tcx.alias_graph.synthesize(def_id, key);
+        for field in 0..2 {
+            let endpoint = self.my_tcx.alias_graph.get_or_create_target(
+                def_id,
+                pair,
+                EdgeLabel::new_field(field),
+            );
+            unsafe {
+                (*endpoint).add_target(channel, EdgeLabel::Deref);
+            }
+        }
     }
 
     fn visit_address_of_or_ref(&mut self, def_id: &DefId, lhs: &Place, rhs: &Place) {
@@ -198,7 +568,8 @@ impl<'a, 'tcx> AliasAnalysis<'a, 'tcx> {
         body: &Body<'tcx>,
     ) {
         match terminator_kind {
-            // TODO: if return a lock?
+            // a returned guard is linked to its lock by the lock set analysis,
+            // through the callee's summary
             rustc_middle::mir::TerminatorKind::Call {
                 func,
                 args,
@@ -218,76 +589,78 @@ impl<'a, 'tcx> AliasAnalysis<'a, 'tcx> {
                                 if let DefPathData::ValueNs(name) =
                                     &def_path.data[def_path.data.len() - 1].data
                                 {
-                                    if is_mutex_method(&def_path_str) {
+                                    if let Some(op) = raw_lock_op(&def_path_str) {
+                                        self.visit_raw_lock_op(def_id, args, destination, op);
+                                    } else if let Some(in_result) = guard_map(&def_path_str) {
+                                        self.visit_guard_map(def_id, args, destination, in_result);
+                                    } else if let Some(through_arc) =
+                                        lock_api_acquire(&def_path_str)
+                                    {
+                                        self.visit_lock_api_acquire(
+                                            def_id,
+                                            args,
+                                            destination,
+                                            through_arc,
+                                        );
+                                    } else if is_try_lock_method(&def_path_str) {
+                                        self.visit_try_lock(def_id, args, destination);
+                                    } else if is_mutex_method(&def_path_str) {
                                         if name.as_str() == "new" {
-                                            assert_eq!(1, args.len());
-                                            match &args[0].node {
-                                                mir::Operand::Copy(_) => {
-                                                    panic!("should not go to this branch!");
-                                                }
-                                                mir::Operand::Constant(_)
-                                                | mir::Operand::Move(_) => {
-                                                    self.my_tcx
-                                                        .alias_graph
-                                                        .resolve_project(def_id, destination);
-                                                }
-                                            }
+                                            self.my_tcx
+                                                .alias_graph
+                                                .resolve_project(def_id, destination);
                                         } else if name.as_str() == "lock" {
-                                            assert_eq!(1, args.len());
-                                            match &args[0].node {
-                                                // must be move _*
-                                                mir::Operand::Constant(_) => todo!(),
-                                                mir::Operand::Copy(p) | mir::Operand::Move(p) => {
-                                                    // if the lock_ref is from the parameters, lock_ref may have no out_vertices
+                                            // guard = mutex::lock( lock_ref ), lock_ref is &mutex,
+                                            // so the lock is its deref target; if the lock_ref is
+                                            // from the parameters, it may have no out_vertices
+                                            match args
+                                                .first()
+                                                .and_then(|arg| self.pointee(def_id, &arg.node))
+                                            {
+                                                Some(lock) => {
                                                     let guard = self
                                                         .my_tcx
                                                         .alias_graph
                                                         .resolve_project(def_id, destination);
-                                                    let lock_ref = self
-                                                        .my_tcx
-                                                        .alias_graph
-                                                        .resolve_project(def_id, p);
-                                                    // guard = mutex::lock( lock_ref )
-                                                    // lock_ref is &mutex, so need to get its deref target
                                                     unsafe {
-                                                        if let Some(lock) = (*lock_ref)
-                                                            .get_out_vertex(&EdgeLabel::Deref)
-                                                        {
-                                                            (*guard).add_target(
-                                                                lock,
-                                                                EdgeLabel::from("Guard"),
-                                                            );
-                                                        } else {
-                                                            let lock = self
-                                                                .my_tcx
-                                                                .alias_graph
-                                                                .get_or_insert_node(
-                                                                    GraphNodeId::new(
-                                                                        def_id.clone(),
-                                                                        None,
-                                                                    ),
-                                                                );
-                                                            (*lock_ref)
-                                                                .add_target(lock, EdgeLabel::Deref);
-                                                            (*guard).add_target(
-                                                                lock,
-                                                                EdgeLabel::from("Guard"),
-                                                            );
-                                                        }
+                                                        (*guard).add_target(
+                                                            lock,
+                                                            EdgeLabel::from("Guard"),
+                                                        );
                                                     }
                                                 }
+                                                None => self.visit_unknown(
+                                                    def_id,
+                                                    destination,
+                                                    "lock of an unknown mutex",
+                                                ),
                                             }
                                         }
+                                    } else if matches!(
+                                        condvar_op(&def_path_str),
+                                        Some(kind) if kind.is_wait()
+                                    ) {
+                                        // a timed wait returns the guard with the timeout result
+                                        let in_tuple = name.as_str().starts_with("wait_timeout");
+                                        self.visit_condvar_wait(
+                                            def_id,
+                                            args,
+                                            destination,
+                                            in_tuple,
+                                        );
+                                    } else if refcell_borrow(&def_path_str).is_some() {
+                                        self.visit_cell_borrow(def_id, args, destination);
+                                    } else if let Some(ChannelOp::Create { .. }) =
+                                        channel_op(&def_path_str)
+                                    {
+                                        self.visit_channel_creation(def_id, bb_index, destination);
                                     } else if is_smart_pointer(&def_path_str) {
                                         if name.as_str() == "new" {
                                             // the same as ref assign
-                                            assert_eq!(1, args.len());
-                                            match &args[0].node {
-                                                mir::Operand::Constant(_)
-                                                | mir::Operand::Copy(_) => {
-                                                    panic!("should not go to this branch!");
-                                                }
-                                                mir::Operand::Move(p) => {
+                                            match args.first().map(|arg| &arg.node) {
+                                                Some(
+                                                    mir::Operand::Move(p) | mir::Operand::Copy(p),
+                                                ) => {
                                                     let smart_ptr = self
                                                         .my_tcx
                                                         .alias_graph
@@ -301,69 +674,65 @@ impl<'a, 'tcx> AliasAnalysis<'a, 'tcx> {
                                                             .add_target(val, EdgeLabel::Deref);
                                                     }
                                                 }
+                                                Some(mir::Operand::Constant(_)) => {
+                                                    self.visit_constant(def_id, destination)
+                                                }
+                                                None => self.visit_unknown(
+                                                    def_id,
+                                                    destination,
+                                                    "smart pointer without a value",
+                                                ),
                                             }
                                         }
+                                    } else if is_async_lock_method(&def_path_str) {
+                                        self.visit_async_lock(def_id, args, destination);
+                                    } else if is_future_poll(&def_path_str) {
+                                        self.visit_future_poll(def_id, args, destination);
+                                    } else if is_future_wrapper(&def_path_str) {
+                                        let wrapped = args.first().and_then(|arg| arg.node.place());
+                                        if let Some(p) = wrapped {
+                                            let wrapper = self
+                                                .my_tcx
+                                                .alias_graph
+                                                .resolve_project(def_id, destination);
+                                            let wrapped =
+                                                self.my_tcx.alias_graph.resolve_project(def_id, &p);
+                                            self.flow(wrapped, wrapper);
+                                        }
                                     } else if name.as_str() == "unwrap" {
-                                        assert_eq!(1, args.len());
-                                        match &args[0].node {
-                                            // must be move _*
-                                            mir::Operand::Constant(_) => {
-                                                self.visit_constant(def_id, destination)
-                                            }
-                                            mir::Operand::Move(p) | mir::Operand::Copy(p) => {
-                                                let unwrap = self
-                                                    .my_tcx
-                                                    .alias_graph
-                                                    .resolve_project(def_id, destination);
-                                                let unwraped = self
-                                                    .my_tcx
-                                                    .alias_graph
-                                                    .resolve_project(def_id, p);
-                                                self.make_alias(unwraped, unwrap);
+                                        match args.first() {
+                                            Some(arg) => {
+                                                self.visit_operand(def_id, destination, &arg.node)
                                             }
+                                            None => self.visit_unknown(
+                                                def_id,
+                                                destination,
+                                                "unwrap without a receiver",
+                                            ),
                                         }
                                     }
                                     // else if name.as_str() == "deref"{
                                     // }
                                     // todo: maybe problematic here
                                     else if name.as_str() == "clone" || name.as_str() == "deref" {
-                                        assert_eq!(1, args.len());
-                                        match &args[0].node {
-                                            // must be copy _*
-                                            mir::Operand::Constant(_) => {
-                                                self.visit_constant(def_id, destination)
-                                            }
-                                            mir::Operand::Move(p) | mir::Operand::Copy(p) => {
-                                                // clone = mutex::lock( cloned_ref )
-                                                // cloned_ref is &be_cloned, so need to get its deref target
-                                                let clone = self
-                                                    .my_tcx
-                                                    .alias_graph
-                                                    .resolve_project(def_id, destination);
-                                                let cloned_ref = self
-                                                    .my_tcx
-                                                    .alias_graph
-                                                    .resolve_project(def_id, p);
-                                                unsafe {
-                                                    // if the clone_ref is from the parameters, clone_ref may have no out_vertices
-                                                    if let Some(cloned) = (*cloned_ref)
-                                                        .get_out_vertex(&EdgeLabel::Deref)
-                                                    {
-                                                        self.make_alias(cloned, clone);
-                                                    } else {
-                                                        let cloned = self
-                                                            .my_tcx
-                                                            .alias_graph
-                                                            .get_or_insert_node(GraphNodeId::new(
-                                                                def_id.clone(),
-                                                                None,
-                                                            ));
-                                                        (*cloned_ref)
-                                                            .add_target(cloned, EdgeLabel::Deref);
-                                                        self.make_alias(cloned, clone);
-                                                    }
+                                        // clone = clone( cloned_ref ), cloned_ref is &be_cloned,
+                                        // so the clone is a copy of its deref target
+                                        match args.first().map(|arg| &arg.node) {
+                                            Some(op) => match self.pointee(def_id, op) {
+                                                Some(cloned) => {
+                                                    let clone = self
+                                                        .my_tcx
+                                                        .alias_graph
+                                                        .resolve_project(def_id, destination);
+                                                    self.flow(cloned, clone);
                                                 }
-                                            }
+                                                None => self.visit_constant(def_id, destination),
+                                            },
+                                            None => self.visit_unknown(
+                                                def_id,
+                                                destination,
+                                                "clone without a receiver",
+                                            ),
                                         }
                                     } else {
                                         let call = Call::new(
@@ -377,7 +746,18 @@ impl<'a, 'tcx> AliasAnalysis<'a, 'tcx> {
                                             destination.clone(),
                                             args.iter().map(|span| span.node.clone()).collect(),
                                         );
-                                        // self.call_graph.add_call(def_id.clone(), call);
+                                        // the crate's own functions bind their parameters
+                                        // and return value to the call
+                                        if fn_id.is_local()
+                                            && self.my_tcx.tcx.is_mir_available(*fn_id)
+                                        {
+                                            self.my_tcx
+                                                .call_graph
+                                                .calls_map
+                                                .entry(def_id.clone())
+                                                .or_default()
+                                                .insert(call);
+                                        }
                                     }
                                 }
                             }
@@ -401,39 +781,100 @@ impl<'a, 'tcx> AliasAnalysis<'a, 'tcx> {
             if iteration_count >= self.num_iteration {
                 break;
             }
-            // todo: redundant clone
-            for (def_id, call_set) in self.my_tcx.call_graph.calls_map.clone().iter() {
-                for call in call_set {
-                    let callee = call.callee();
-                    let ret = call.ret();
-                    // 1. add ret's constrain: ret in caller = callee()
-                    let ret_node = self.my_tcx.alias_graph.resolve_project(def_id, ret);
-                    let callee_ret = self
-                        .my_tcx
-                        .alias_graph
-                        .get_or_insert_node(GraphNodeId::new(callee.clone(), Some(0)));
-                    self.make_alias(ret_node, callee_ret);
-                    // 2. add args' constrain
-                    // assert_eq!(call.args().len(), self.tcx.optimized_mir(callee).arg_count);
-                    for (index, arg) in call.args().iter().enumerate() {
-                        match arg {
-                            mir::Operand::Copy(p) | mir::Operand::Move(p) => {
-                                let arg = self.my_tcx.alias_graph.resolve_project(def_id, p);
-                                let param = self.my_tcx.alias_graph.get_or_insert_node(
-                                    GraphNodeId::new(callee.clone(), Some(index + 1)),
-                                );
-                                self.make_alias(param, arg);
-                            }
-                            mir::Operand::Constant(_) => (),
+            // a callee's nodes are copied into its callers for `context_depth` levels,
+            // so two call sites of a helper keep their locks apart; deeper, or in a
+            // recursion, every call site binds the callee's own nodes
+            let context_depth = self.my_tcx.options.context_depth as usize;
+            let alias_mode = self.my_tcx.options.alias_mode;
+            let mut depths: FxHashMap<DefId, usize> = FxHashMap::default();
+            for def_id in self.my_tcx.call_graph.topo.clone() {
+                let mut depth = 0;
+                // todo: redundant clone
+                let calls = self.my_tcx.call_graph.calls_map.get(&def_id).cloned();
+                for call in calls.unwrap_or_default().iter() {
+                    // a skipped function's nodes miss what it does, so bind nothing to them
+                    if self.my_tcx.is_skipped(&def_id) || self.my_tcx.is_skipped(call.callee()) {
+                        continue;
+                    }
+                    let copied = match depths.get(call.callee()) {
+                        Some(callee_depth) if *callee_depth < context_depth => {
+                            depth = depth.max(callee_depth + 1);
+                            true
                         }
+                        _ => false,
+                    };
+                    if let Err(reason) = isolate(|| self.bind_call(&def_id, call, copied)) {
+                        self.my_tcx.skip(def_id, "alias analysis", reason);
                     }
                 }
+                depths.insert(def_id, depth);
+            }
+            let alias_graph = &mut self.my_tcx.alias_graph;
+            let solved = isolate(|| match alias_mode {
+                AliasMode::Unification => alias_graph.qirun_algorithm(),
+                AliasMode::Inclusion => alias_graph.solve_inclusions(),
+                AliasMode::Demand => alias_graph.set_demand_driven(),
+            });
+            if let Err(reason) = solved {
+                let analysis = format!("{} alias solver", alias_mode);
+                self.my_tcx.fail(analysis, reason);
             }
-            self.my_tcx.alias_graph.qirun_algorithm();
             iteration_count += 1;
         }
     }
 
+    /// ret = callee(args): bind the arguments to the callee's parameters and `ret` to its
+    /// return value, or to a copy of the callee's nodes made for this call site
+    fn bind_call(&mut self, def_id: &DefId, call: &Call<'tcx>, copied: bool) {
+        let callee = call.callee();
+        let mut interface: Vec<*mut AliasGraphNode> = (0..=call.args().len())
+            .map(|index| {
+                self.my_tcx
+                    .alias_graph
+                    .get_or_insert_node(GraphNodeId::local(*callee, index))
+            })
+            .collect();
+        if copied {
+            let block = call.call_site().1.block.as_usize();
+            interface = self
+                .my_tcx
+                .alias_graph
+                .copy_nodes(def_id, block, &interface, MAX_COPY_DEPTH);
+        }
+        // binding a node may merge another one away, so look them up again
+        let interface: Vec<GraphNodeId> =
+            interface.iter().map(|node| unsafe { (**node).id.clone() }).collect();
+        // 1. add ret's constrain: ret in caller = callee()
+        let ret_node = self.my_tcx.alias_graph.resolve_project(def_id, call.ret());
+        let callee_ret = self.my_tcx.alias_graph.get_node(&interface[0]).unwrap();
+        self.flow(callee_ret, ret_node);
+        // 2. add args' constrain
+        for (index, arg) in call.args().iter().enumerate() {
+            match arg {
+                mir::Operand::Copy(p) | mir::Operand::Move(p) => {
+                    let arg = self.my_tcx.alias_graph.resolve_project(def_id, p);
+                    let param = self
+                        .my_tcx
+                        .alias_graph
+                        .get_node(&interface[index + 1])
+                        .unwrap();
+                    self.flow(arg, param);
+                }
+                // a reference to a static points to the same node in every function
+                mir::Operand::Constant(_) => {
+                    if let Some(static_def_id) = static_ref(self.my_tcx.tcx, arg) {
+                        let param = self
+                            .my_tcx
+                            .alias_graph
+                            .get_node(&interface[index + 1])
+                            .unwrap();
+                        self.point_to_static(param, static_def_id);
+                    }
+                }
+            }
+        }
+    }
+
     fn make_alias(
         &mut self,
         node_x: *mut AliasGraphNode,
@@ -441,4 +882,79 @@ impl<'a, 'tcx> AliasAnalysis<'a, 'tcx> {
     ) -> *mut AliasGraphNode {
         self.my_tcx.alias_graph.combine(node_x, node_y)
     }
+
+    /// the value of `from` is copied or moved into `to`
+    fn flow(&mut self, from: *mut AliasGraphNode, to: *mut AliasGraphNode) {
+        match self.my_tcx.options.alias_mode {
+            AliasMode::Unification => {
+                self.make_alias(to, from);
+            }
+            AliasMode::Inclusion | AliasMode::Demand => {
+                self.my_tcx.alias_graph.add_inclusion(from, to);
+            }
+        }
+    }
+}
+
+/// the field of its state each (variant, field) of a coroutine body stands for: the local
+/// it saves, numbered after the upvars, e.g. a guard kept across an await apart from the
+/// future awaited before it
+fn coroutine_fields<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    def_id: &DefId,
+    body: &Body<'tcx>,
+) -> FxHashMap<(usize, usize), usize> {
+    let mut fields = FxHashMap::default();
+    let Some(layout) = body.coroutine_layout_raw() else {
+        return fields;
+    };
+    let ty::Coroutine(_, args) = tcx.type_of(*def_id).instantiate_identity().kind() else {
+        return fields;
+    };
+    let upvars = args.as_coroutine().upvar_tys().len();
+    for (variant, saved_locals) in layout.variant_fields.iter_enumerated() {
+        for (field, saved_local) in saved_locals.iter_enumerated() {
+            fields.insert(
+                (variant.as_usize(), field.as_usize()),
+                upvars + saved_local.as_usize(),
+            );
+        }
+    }
+    fields
+}
+
+/// the locals assigned one constant `usize` and nothing else, e.g. the `_3 = const 1_usize`
+/// of `locks[1]` when the index is not folded into the place
+fn index_constants<'tcx>(tcx: TyCtxt<'tcx>, body: &Body<'tcx>) -> FxHashMap<usize, u64> {
+    let mut constants = FxHashMap::default();
+    let mut assigned = FxHashSet::default();
+    for data in body.basic_blocks.iter() {
+        for statement in data.statements.iter() {
+            let mir::StatementKind::Assign(assign) = &statement.kind else {
+                continue;
+            };
+            let (lhs, rhs) = (&assign.0, &assign.1);
+            let local = lhs.local.as_usize();
+            if !assigned.insert(local) || !lhs.projection.is_empty() {
+                constants.remove(&local);
+                continue;
+            }
+            if let Rvalue::Use(mir::Operand::Constant(constant)) = rhs {
+                if *constant.ty().kind() != ty::Uint(ty::UintTy::Usize) {
+                    continue;
+                }
+                if let Some(value) = constant
+                    .const_
+                    .try_eval_target_usize(tcx, tcx.param_env(body.source.def_id()))
+                {
+                    constants.insert(local, value);
+                }
+            }
+        }
+        if let TerminatorKind::Call { destination, .. } = &data.terminator().kind {
+            assigned.insert(destination.local.as_usize());
+            constants.remove(&destination.local.as_usize());
+        }
+    }
+    constants
 }
@@ -1,18 +1,44 @@
-use std::{collections::VecDeque, fmt::format, rc::Rc, thread::current};
+use std::{cell::RefCell, collections::VecDeque, fmt::format, rc::Rc, sync::Arc, thread::current};
 
 use rustc_hash::{FxHashMap, FxHashSet};
-use rustc_hir::def_id::DefId;
-use rustc_middle::mir::{self, Place};
+use rustc_hir::def_id::{DefId, CRATE_DEF_ID};
+use rustc_middle::mir::{self, Place, PlaceElem};
 
 use crate::analysis::alias::node::EdgeLabel;
 
-use super::node::{self, AliasGraphNode, GraphNodeId};
+use super::node::{self, AliasGraphNode, GraphNodeId, NodeKey};
+
+mod inclusion;
+mod query;
+
+use query::AliasQuery;
 
 #[derive(Clone)]
 pub struct AliasGraph {
     // might be problematic, as Rust hashes the raw pointer literally, not according to its data
     nodes: FxHashSet<*mut AliasGraphNode>,
     node_map: FxHashMap<GraphNodeId, *mut AliasGraphNode>,
+    // the inclusion mode: node -> the nodes flowing into it, by the ids they had then
+    inclusions: FxHashMap<GraphNodeId, FxHashSet<GraphNodeId>>,
+    // targets created for a pointer, guard or future without one, which stand for
+    // whatever it points to rather than for a location of their own
+    summaries: FxHashSet<GraphNodeId>,
+    // targets standing for every element of an array indexed by a value the analysis
+    // cannot tell, which may be any of the locations its elements are
+    collapsed: FxHashSet<GraphNodeId>,
+    // the locals of each function holding one known `usize`, which index as constants
+    index_constants: FxHashMap<DefId, FxHashMap<usize, u64>>,
+    // the field of its state each (variant, field) of a coroutine stands for, as the
+    // variants of a suspended coroutine number their fields from 0 each
+    coroutine_fields: FxHashMap<DefId, FxHashMap<(usize, usize), usize>>,
+    // the inclusion mode, once solved: a target made for a view is made for the
+    // locations behind it too, which flow into it
+    inclusions_solved: bool,
+    // the demand-driven mode: the graph is left unsolved, and a node looked up
+    // is given the locations and targets the values flowing into it have
+    demand_driven: bool,
+    demanded: FxHashSet<*mut AliasGraphNode>,
+    query: RefCell<AliasQuery>,
 }
 
 impl Drop for AliasGraph {
@@ -30,7 +56,32 @@ impl AliasGraph {
         AliasGraph {
             nodes: FxHashSet::default(),
             node_map: FxHashMap::default(),
+            inclusions: FxHashMap::default(),
+            summaries: FxHashSet::default(),
+            collapsed: FxHashSet::default(),
+            index_constants: FxHashMap::default(),
+            coroutine_fields: FxHashMap::default(),
+            inclusions_solved: false,
+            demand_driven: false,
+            demanded: FxHashSet::default(),
+            query: RefCell::default(),
+        }
+    }
+
+    /// a fresh node of `def_id` for `key`, the first one of the key no node has had; its
+    /// id only depends on the key, so it is the same whichever nodes are made before
+    pub fn synthesize(&mut self, def_id: &DefId, key: NodeKey) -> *mut AliasGraphNode {
+        let mut id = GraphNodeId::new(*def_id, key);
+        while self.node_map.contains_key(&id) {
+            id.nth += 1;
         }
+        self.add_node(id)
+    }
+
+    /// the node of the values the analyses cannot tell the origin of, e.g. an integer cast
+    /// to a pointer; all of them may alias, as they share it
+    pub fn unknown(&mut self) -> *mut AliasGraphNode {
+        self.get_or_insert_node(GraphNodeId::new(CRATE_DEF_ID.to_def_id(), NodeKey::Unknown))
     }
 
     pub fn find_vertex(&self, val: *mut AliasGraphNode) -> Option<*mut AliasGraphNode> {
@@ -38,23 +89,16 @@ impl AliasGraph {
     }
 
     pub fn add_node(&mut self, id: GraphNodeId) -> *mut AliasGraphNode {
-        let node_ptr = AliasGraphNode::new(id);
+        let node_ptr = AliasGraphNode::new(id.clone());
         self.nodes.insert(node_ptr);
         self.node_map.insert(id, node_ptr);
         node_ptr
     }
 
     pub fn get_or_insert_node(&mut self, id: GraphNodeId) -> *mut AliasGraphNode {
-        unsafe {
-            let node_ptr = AliasGraphNode::new(id);
-            match self.node_map.get(&(*node_ptr).id) {
-                Some(ptr) => *ptr,
-                None => {
-                    self.nodes.insert(node_ptr);
-                    self.node_map.insert((*node_ptr).id, node_ptr);
-                    node_ptr
-                }
-            }
+        match self.node_map.get(&id) {
+            Some(ptr) => *ptr,
+            None => self.add_node(id),
         }
     }
 
@@ -119,7 +163,7 @@ impl AliasGraph {
             // Move equivalence class of NodeY to NodeX
             let vals = (*node_y).get_alias_set();
             for &val in (*vals).iter() {
-                self.node_map.insert((*val).id, node_x);
+                self.node_map.insert((*val).id.clone(), node_x);
             }
             (*node_y).mv_alias_set_to(node_x);
 
@@ -162,7 +206,7 @@ impl AliasGraph {
 
                     let y_equiv_set = (*y).get_alias_set();
                     for &val in (*y_equiv_set).iter() {
-                        self.node_map.insert((*val).id, x);
+                        self.node_map.insert((*val).id.clone(), x);
                     }
                     (*y).mv_alias_set_to(x);
 
@@ -218,69 +262,418 @@ impl AliasGraph {
 
     pub fn resolve_project(&mut self, def_id: &DefId, p: &Place) -> *mut AliasGraphNode {
         unsafe {
-            let cur_node_id = GraphNodeId::new(def_id.clone(), Some(p.local.as_usize()));
+            let cur_node_id = GraphNodeId::local(*def_id, p.local.as_usize());
             let mut cur_node = self.get_or_insert_node(cur_node_id);
             // let mut current_node_set = Box::into_raw(Box::new(FxHashSet::default()));
             // (*current_node_set).insert(cur_node);
+            let mut variant = None;
             for projection in p.projection {
+                let downcast = variant.take();
                 match &projection {
                     // TODO: complex types
                     mir::ProjectionElem::Deref => {
                         // (*p).* ... get q of all p --deref--> q; if there's no such q, create one
-                        let deref_label = EdgeLabel::Deref;
-                        if let Some(targets) = (*cur_node).get_out_vertices(&deref_label) {
-                            // if the set is empty
-                            if (*targets).is_empty() {
-                                let target_node =
-                                    self.add_node(GraphNodeId::new(def_id.clone(), None));
-                                (*cur_node).add_target(target_node, deref_label);
-                                // (*current_node_set).insert(target_node);
-                                cur_node = target_node;
-                            } else {
-                                cur_node = *(*targets).iter().next().unwrap();
-                            }
-                        } else {
-                            let target_id = GraphNodeId::new(def_id.clone(), None);
-                            let target_node = self.add_node(target_id);
-                            (*cur_node).add_target(target_node, deref_label);
-                            // (*current_node_set).insert(target_node);
-                            cur_node = target_node;
-                        }
+                        cur_node = self.get_or_create_target(def_id, cur_node, EdgeLabel::Deref);
                     }
                     mir::ProjectionElem::Field(field_idx, _) => {
-                        let field_label = EdgeLabel::new_field(field_idx.as_usize());
-                        if let Some(targets) = (*cur_node).get_out_vertices(&field_label) {
-                            // if the set is empty
-                            if (*targets).is_empty() {
-                                let target_node =
-                                    self.add_node(GraphNodeId::new(def_id.clone(), None));
-                                (*cur_node).add_target(target_node, field_label);
-                                // (*current_node_set).insert(target_node);
-                                cur_node = target_node;
-                            } else {
-                                cur_node = *(*targets).iter().next().unwrap();
-                            }
-                        } else {
-                            let target_id = GraphNodeId::new(def_id.clone(), None);
-                            let target_node = self.add_node(target_id);
-                            (*cur_node).add_target(target_node, field_label);
-                            // (*current_node_set).insert(target_node);
-                            cur_node = target_node;
-                        }
+                        let field_label = self.field_label(def_id, downcast, field_idx.as_usize());
+                        cur_node = self.get_or_create_target(def_id, cur_node, field_label);
+                    }
+                    mir::ProjectionElem::Downcast(_, variant_idx) => {
+                        variant = Some(variant_idx.as_usize());
                     }
-                    mir::ProjectionElem::Index(_) => todo!(),
-                    mir::ProjectionElem::ConstantIndex { .. } => todo!(),
-                    mir::ProjectionElem::Subslice { .. } => todo!(),
-                    mir::ProjectionElem::Downcast(_, _) => (),
-                    mir::ProjectionElem::OpaqueCast(_) => todo!(),
-                    mir::ProjectionElem::Subtype(_) => todo!(),
+                    mir::ProjectionElem::Index(_) | mir::ProjectionElem::ConstantIndex { .. } => {
+                        let label = self.index_label(def_id, cur_node, projection);
+                        cur_node = self.get_or_create_target(def_id, cur_node, label);
+                    }
+                    // a subslice's elements are the slice's, and the rest are the same
+                    // location seen at another type
+                    mir::ProjectionElem::Subslice { .. }
+                    | mir::ProjectionElem::OpaqueCast(_)
+                    | mir::ProjectionElem::Subtype(_) => (),
                 }
             }
             // current_node_set
+            self.demand(cur_node);
             cur_node
         }
     }
 
+    /// get one target of `node` through `label`; if there's no such target, create one
+    pub fn get_or_create_target(
+        &mut self,
+        def_id: &DefId,
+        node: *mut AliasGraphNode,
+        label: EdgeLabel,
+    ) -> *mut AliasGraphNode {
+        let created = unsafe { (*node).get_out_vertex(&label).is_none() };
+        let target = self.target_or_new(def_id, node, label);
+        if created {
+            self.share_target(node, label, target);
+        }
+        self.demand(target);
+        target
+    }
+
+    /// once the graph is solved, a target made for a view is made for the locations
+    /// behind it too; otherwise the views of a location given no target, e.g. two copies
+    /// of a closure's capture, would each get a target of their own
+    fn share_target(
+        &mut self,
+        view: *mut AliasGraphNode,
+        label: EdgeLabel,
+        target: *mut AliasGraphNode,
+    ) {
+        if self.demand_driven {
+            // a query finds it through the values of the view
+            for location in self.locations(view).unwrap_or_default() {
+                if location != view {
+                    let def_id = unsafe { (*location).id.def_id };
+                    self.target_or_new(&def_id, location, label);
+                }
+            }
+        } else if self.inclusions_solved {
+            self.share_solved_target(view, label, target);
+        }
+    }
+
+    fn target_or_new(
+        &mut self,
+        def_id: &DefId,
+        node: *mut AliasGraphNode,
+        label: EdgeLabel,
+    ) -> *mut AliasGraphNode {
+        unsafe {
+            if let Some(target) = (*node).get_out_vertex(&label) {
+                return target;
+            }
+            let key = NodeKey::Target { base: Arc::new((*node).id.clone()), label };
+            let target_node = self.synthesize(def_id, key);
+            (*node).add_target(target_node, label);
+            if !label.is_part() {
+                self.summaries.insert((*target_node).id.clone());
+            }
+            // so is a part of any element
+            if label == EdgeLabel::Index || label.is_part() && self.is_collapsed(node) {
+                self.collapsed.insert((*target_node).id.clone());
+            }
+            target_node
+        }
+    }
+
+    /// record the fields of a coroutine's state its variants' fields stand for
+    pub fn set_coroutine_fields(
+        &mut self,
+        def_id: DefId,
+        fields: FxHashMap<(usize, usize), usize>,
+    ) {
+        self.coroutine_fields.insert(def_id, fields);
+    }
+
+    /// the label of a field, maybe of a variant; the variants of an enum share their
+    /// fields, unless they are a coroutine's, whose fields are the locals it saves
+    pub(super) fn field_label(
+        &self,
+        def_id: &DefId,
+        variant: Option<usize>,
+        field: usize,
+    ) -> EdgeLabel {
+        let saved = variant.and_then(|variant| {
+            self.coroutine_fields
+                .get(def_id)?
+                .get(&(variant, field))
+                .copied()
+        });
+        EdgeLabel::new_field(saved.unwrap_or(field))
+    }
+
+    /// record the locals of a function holding one known `usize`
+    pub fn set_index_constants(&mut self, def_id: DefId, constants: FxHashMap<usize, u64>) {
+        self.index_constants.insert(def_id, constants);
+    }
+
+    /// the label an index projection of `node` reaches the element through: its own for
+    /// a constant offset from the start, unless the elements are already collapsed, and
+    /// the collapsed one otherwise
+    pub(super) fn index_label(
+        &self,
+        def_id: &DefId,
+        node: *mut AliasGraphNode,
+        projection: PlaceElem,
+    ) -> EdgeLabel {
+        let offset = match projection {
+            mir::ProjectionElem::ConstantIndex {
+                offset,
+                from_end: false,
+                ..
+            } => Some(offset),
+            mir::ProjectionElem::Index(local) => self
+                .index_constants
+                .get(def_id)
+                .and_then(|constants| constants.get(&local.as_usize()).copied()),
+            _ => None,
+        };
+        let Some(offset) = offset else {
+            return EdgeLabel::Index;
+        };
+        let element = EdgeLabel::Element(offset as usize);
+        unsafe {
+            // an array built by repeating a value has its elements collapsed already
+            if (*node).out_num_vertices(&EdgeLabel::Index) > 0
+                && (*node).out_num_vertices(&element) == 0
+            {
+                return EdgeLabel::Index;
+            }
+        }
+        element
+    }
+
+    /// whether a node stands for any of the elements of an array, so a lock it names may
+    /// be another one than the lock of the same name acquired before
+    pub fn is_collapsed(&self, node: *mut AliasGraphNode) -> bool {
+        unsafe {
+            (*(*node).alias_set)
+                .iter()
+                .any(|member| self.collapsed.contains(&(**member).id))
+        }
+    }
+
+    /// the number of arrays whose elements one node stands for
+    pub fn collapsed_count(&self) -> usize {
+        self.collapsed
+            .iter()
+            .filter(|id| matches!(id.key, NodeKey::Target { label: EdgeLabel::Index, .. }))
+            .count()
+    }
+
+    /// `from` flows into `to` in the inclusion mode; false if it already did
+    pub fn add_inclusion(&mut self, from: *mut AliasGraphNode, to: *mut AliasGraphNode) -> bool {
+        unsafe {
+            from != to
+                && self
+                    .inclusions
+                    .entry((*to).id.clone())
+                    .or_default()
+                    .insert((*from).id.clone())
+        }
+    }
+
+    /// the ids merged into a node; a view's alias set holds other nodes' ids once solved
+    fn ids_of(&self, node: *mut AliasGraphNode) -> Vec<GraphNodeId> {
+        unsafe {
+            let mut ids = vec![(*node).id.clone()];
+            for &member in (*(*node).alias_set).iter() {
+                let id = &(*member).id;
+                if *id != (*node).id && self.node_map.get(id) == Some(&node) {
+                    ids.push(id.clone());
+                }
+            }
+            ids
+        }
+    }
+
+    /// whether a node stands for whatever its pointer, guard or future points to
+    fn is_summary(&self, node: *mut AliasGraphNode) -> bool {
+        self.ids_of(node)
+            .iter()
+            .any(|id| self.summaries.contains(id))
+    }
+
+    /// the representatives of the nodes flowing into `node`
+    fn sources_of(&self, node: *mut AliasGraphNode) -> Vec<*mut AliasGraphNode> {
+        let mut sources = vec![];
+        for id in self.ids_of(node) {
+            let Some(froms) = self.inclusions.get(&id) else {
+                continue;
+            };
+            for from in froms.iter() {
+                if let Some(from) = self.get_node(from) {
+                    if from != node && !sources.contains(&from) {
+                        sources.push(from);
+                    }
+                }
+            }
+        }
+        sources
+    }
+
+    fn targets_of(&self, node: *mut AliasGraphNode, label: &EdgeLabel) -> Vec<*mut AliasGraphNode> {
+        unsafe {
+            match (*node).get_out_vertices(label) {
+                Some(targets) => (*targets).iter().copied().collect(),
+                None => vec![],
+            }
+        }
+    }
+
+    /// the representative node of an id, if the id has been seen
+    pub fn get_node(&self, id: &GraphNodeId) -> Option<*mut AliasGraphNode> {
+        self.node_map.get(id).copied()
+    }
+
+    /// copy the nodes reachable from the roots within `max_depth` edges or inclusions,
+    /// and the edges and inclusions between them, into fresh nodes of `owner` made for
+    /// the call terminating `block`; returns the copies of the roots
+    pub fn copy_nodes(
+        &mut self,
+        owner: &DefId,
+        block: usize,
+        roots: &[*mut AliasGraphNode],
+        max_depth: usize,
+    ) -> Vec<*mut AliasGraphNode> {
+        let mut copies: FxHashMap<*mut AliasGraphNode, *mut AliasGraphNode> =
+            FxHashMap::default();
+        let mut work_list = VecDeque::new();
+        for &root in roots {
+            if !copies.contains_key(&root) {
+                copies.insert(root, self.copy_node(owner, block, root));
+                work_list.push_back((root, 0));
+            }
+        }
+        while let Some((node, depth)) = work_list.pop_front() {
+            if depth >= max_depth {
+                continue;
+            }
+            let copy = copies[&node];
+            unsafe {
+                let edges: Vec<(EdgeLabel, Vec<*mut AliasGraphNode>)> = (*(*node).successors)
+                    .iter()
+                    .map(|(label, targets)| (*label, (**targets).iter().copied().collect()))
+                    .collect();
+                for (label, targets) in edges {
+                    for target in targets {
+                        let target_copy = match copies.get(&target) {
+                            Some(target_copy) => *target_copy,
+                            None => {
+                                let target_copy = self.copy_node(owner, block, target);
+                                copies.insert(target, target_copy);
+                                work_list.push_back((target, depth + 1));
+                                target_copy
+                            }
+                        };
+                        (*copy).add_target(target_copy, label);
+                    }
+                }
+            }
+            for source in self.sources_of(node) {
+                let source_copy = match copies.get(&source) {
+                    Some(source_copy) => *source_copy,
+                    None => {
+                        let source_copy = self.copy_node(owner, block, source);
+                        copies.insert(source, source_copy);
+                        work_list.push_back((source, depth + 1));
+                        source_copy
+                    }
+                };
+                self.add_inclusion(source_copy, copy);
+            }
+        }
+        roots.iter().map(|root| copies[root]).collect()
+    }
+
+    /// a fresh node of `owner` standing for the same kind of location as `node`
+    fn copy_node(
+        &mut self,
+        owner: &DefId,
+        block: usize,
+        node: *mut AliasGraphNode,
+    ) -> *mut AliasGraphNode {
+        let key = unsafe { NodeKey::Copy { block, node: Arc::new((*node).id.clone()) } };
+        let copy = self.synthesize(owner, key);
+        if self.is_summary(node) {
+            unsafe {
+                self.summaries.insert((*copy).id.clone());
+            }
+        }
+        if self.is_collapsed(node) {
+            unsafe {
+                self.collapsed.insert((*copy).id.clone());
+            }
+        }
+        copy
+    }
+
+    /// shortest label path from `from` to `to`, ignoring `Guard` and `Pending` edges,
+    /// as a guard or a future does not give access to the lock's location
+    pub fn find_path(
+        &self,
+        from: *mut AliasGraphNode,
+        to: *mut AliasGraphNode,
+        max_depth: usize,
+    ) -> Option<Vec<EdgeLabel>> {
+        let mut visited = FxHashSet::default();
+        let mut work_list = VecDeque::new();
+        visited.insert(from);
+        work_list.push_back((from, vec![]));
+        while let Some((node, path)) = work_list.pop_front() {
+            if node == to {
+                return Some(path);
+            }
+            if path.len() >= max_depth {
+                continue;
+            }
+            unsafe {
+                // the lowest labels first, so a coroutine's upvars come before the locals
+                // it saves, which are numbered after them
+                let mut successors: Vec<_> = (*(*node).successors).iter().collect();
+                successors.sort_by_key(|(label, _)| **label);
+                for (label, targets) in successors {
+                    if matches!(label, EdgeLabel::Guard | EdgeLabel::Pending) {
+                        continue;
+                    }
+                    for &target in (**targets).iter() {
+                        if visited.insert(target) {
+                            let mut next = path.clone();
+                            next.push(*label);
+                            work_list.push_back((target, next));
+                        }
+                    }
+                }
+            }
+        }
+        if self.demand_driven {
+            return self.find_path_back(from, to, max_depth);
+        }
+        None
+    }
+
+    /// guards owned by a value, i.e. reachable through fields and elements only, with their paths
+    /// and the locks they guard; dropping the value drops them all
+    pub fn find_guards(
+        &self,
+        from: *mut AliasGraphNode,
+        max_depth: usize,
+    ) -> Vec<(Vec<EdgeLabel>, *mut AliasGraphNode)> {
+        let mut guards = vec![];
+        let mut visited = FxHashSet::default();
+        let mut work_list = VecDeque::new();
+        visited.insert(from);
+        work_list.push_back((from, vec![]));
+        while let Some((node, path)) = work_list.pop_front() {
+            unsafe {
+                if let Some(lock) = (*node).get_out_vertex(&EdgeLabel::Guard) {
+                    guards.push((path.clone(), lock));
+                }
+                if path.len() >= max_depth {
+                    continue;
+                }
+                for (label, targets) in (*(*node).successors).iter() {
+                    if !label.is_part() {
+                        continue;
+                    }
+                    for &target in (**targets).iter() {
+                        if visited.insert(target) {
+                            let mut next = path.clone();
+                            next.push(*label);
+                            work_list.push_back((target, next));
+                        }
+                    }
+                }
+            }
+        }
+        guards
+    }
+
     pub fn print(&self) {
         for &node_ptr in &self.nodes {
             unsafe {
@@ -368,8 +761,8 @@ mod tests {
         let mut graph = AliasGraph::new();
 
         // add 2 nodes
-        let node1 = graph.add_node(GraphNodeId::new(DefId::local(DefIndex::from_u32(1)), None));
-        let node2 = graph.add_node(GraphNodeId::new(DefId::local(DefIndex::from_u32(2)), None));
+        let node1 = graph.add_node(GraphNodeId::local(DefId::local(DefIndex::from_u32(1)), 0));
+        let node2 = graph.add_node(GraphNodeId::local(DefId::local(DefIndex::from_u32(2)), 0));
 
         unsafe {
             let label1 = EdgeLabel::Deref;
@@ -399,8 +792,8 @@ mod tests {
         let mut graph = AliasGraph::new();
 
         // add 2 nodes
-        let node1 = graph.add_node(GraphNodeId::new(DefId::local(DefIndex::from_u32(1)), None));
-        let node2 = graph.add_node(GraphNodeId::new(DefId::local(DefIndex::from_u32(2)), None));
+        let node1 = graph.add_node(GraphNodeId::local(DefId::local(DefIndex::from_u32(1)), 0));
+        let node2 = graph.add_node(GraphNodeId::local(DefId::local(DefIndex::from_u32(2)), 0));
         unsafe {
             // move node1's set into node2
             let label1 = EdgeLabel::Deref;
@@ -422,9 +815,9 @@ mod tests {
         let mut graph = AliasGraph::new();
 
         // add 2 nodes
-        let node1 = graph.add_node(GraphNodeId::new(DefId::local(DefIndex::from_u32(1)), None));
-        let node2 = graph.add_node(GraphNodeId::new(DefId::local(DefIndex::from_u32(2)), None));
-        let node3 = graph.add_node(GraphNodeId::new(DefId::local(DefIndex::from_u32(3)), None));
+        let node1 = graph.add_node(GraphNodeId::local(DefId::local(DefIndex::from_u32(1)), 0));
+        let node2 = graph.add_node(GraphNodeId::local(DefId::local(DefIndex::from_u32(2)), 0));
+        let node3 = graph.add_node(GraphNodeId::local(DefId::local(DefIndex::from_u32(3)), 0));
 
         unsafe {
             let label1 = EdgeLabel::Deref;
@@ -443,4 +836,80 @@ mod tests {
         println!("Remove node2 from node3's target\n");
         graph.print_graph();
     }
+
+    #[test]
+    fn test_synthesize() {
+        let mut graph = AliasGraph::new();
+        let def_id = DefId::local(DefIndex::from_u32(1));
+        let local = graph.get_or_insert_node(GraphNodeId::local(def_id, 1));
+        let target = graph.get_or_create_target(&def_id, local, EdgeLabel::Deref);
+        // a target created again for the same key is a node of its own
+        unsafe { (*local).remove_target(target, &EdgeLabel::Deref) };
+        let again = graph.get_or_create_target(&def_id, local, EdgeLabel::Deref);
+        let other = DefId::local(DefIndex::from_u32(2));
+        let copy = graph.copy_nodes(&other, 0, &[local], 0)[0];
+
+        let id = |node: *mut AliasGraphNode| unsafe { (*node).id.clone() };
+        let base = Arc::new(id(local));
+        let key = NodeKey::Target { base: base.clone(), label: EdgeLabel::Deref };
+        assert_eq!(id(target), GraphNodeId::new(def_id, key.clone()));
+        assert_eq!(id(again), GraphNodeId { def_id, key, nth: 1 });
+        let key = NodeKey::Copy { block: 0, node: base };
+        assert_eq!(id(copy), GraphNodeId::new(other, key));
+    }
+
+    #[test]
+    fn test_synthesize_in_any_order() {
+        let def_id = DefId::local(DefIndex::from_u32(1));
+        // (*_1).0 and (*_2), made one after the other or the other way round
+        let ids = |first: usize| {
+            let mut graph = AliasGraph::new();
+            let mut ids = FxHashSet::default();
+            for local in [first, 3 - first] {
+                let node = graph.get_or_insert_node(GraphNodeId::local(def_id, local));
+                let mut target = graph.get_or_create_target(&def_id, node, EdgeLabel::Deref);
+                if local == 1 {
+                    target = graph.get_or_create_target(&def_id, target, EdgeLabel::Field(0));
+                }
+                ids.insert(unsafe { (*target).id.clone() });
+            }
+            ids
+        };
+        assert_eq!(ids(1), ids(2));
+    }
+
+    #[test]
+    fn test_locks_of_unified_set() {
+        let mut graph = AliasGraph::new();
+        let def_id = DefId::local(DefIndex::from_u32(1));
+        let helper = DefId::local(DefIndex::from_u32(2));
+        let mutex = graph.get_or_insert_node(GraphNodeId::local(def_id, 1));
+        let param = graph.get_or_insert_node(GraphNodeId::local(helper, 1));
+        let copy = graph.copy_nodes(&def_id, 3, &[param], 0)[0];
+        // the copy of the helper's parameter made for the call is the caller's mutex
+        let merged = graph.combine(copy, mutex);
+
+        let locks = crate::analysis::locks_of(&graph, merged);
+        assert_eq!(locks.len(), 1);
+        assert_eq!(locks[0].id, GraphNodeId::local(def_id, 1));
+        assert_eq!(crate::analysis::locks_of(&graph, param).len(), 1);
+    }
+
+    #[test]
+    fn test_collapsed() {
+        let mut graph = AliasGraph::new();
+        let def_id = DefId::local(DefIndex::from_u32(1));
+        let array = graph.get_or_insert_node(GraphNodeId::local(def_id, 1));
+        // _1 = [move _2, move _3]; each element at a constant offset is a node of its own
+        let first = graph.get_or_create_target(&def_id, array, EdgeLabel::Element(0));
+        let second = graph.get_or_create_target(&def_id, array, EdgeLabel::Element(1));
+        // _1[_4] may be any of them
+        let any = graph.get_or_create_target(&def_id, array, EdgeLabel::Index);
+        let field = graph.get_or_create_target(&def_id, any, EdgeLabel::Field(0));
+
+        assert_ne!(first, second);
+        assert!(!graph.is_collapsed(first) && !graph.is_collapsed(second));
+        assert!(graph.is_collapsed(any) && graph.is_collapsed(field));
+        assert_eq!(graph.collapsed_count(), 1);
+    }
 }
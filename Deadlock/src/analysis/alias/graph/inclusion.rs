@@ -0,0 +1,392 @@
+//! Inclusion-based solving of the alias graph.
+//!
+//! In the inclusion mode, `a = b` records that `b` flows into `a` instead of merging
+//! the two nodes. A node something flows into is a view of those nodes: its targets
+//! through `Deref`, `Guard` and `Pending` include theirs, its fields are theirs, and
+//! once solved its alias set holds the locations behind it, so a lock reached through
+//! a view may be any of the locks flowing into it, while they stay apart elsewhere.
+
+use std::collections::VecDeque;
+
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::analysis::alias::node::{AliasGraphNode, EdgeLabel};
+
+use super::AliasGraph;
+
+/// the most nodes solving may add, as a multiple of the nodes of the graph;
+/// a recursive type flowing through a loop would grow fields forever
+const NODE_BUDGET: usize = 2;
+
+type Node = *mut AliasGraphNode;
+
+impl AliasGraph {
+    /// propagate the inclusions until nothing changes, merging the nodes flowing into
+    /// each other, then give every view the alias set of the locations behind it
+    pub fn solve_inclusions(&mut self) {
+        self.inclusions_solved = false;
+        let mut budget = self.nodes.len() * NODE_BUDGET;
+        loop {
+            self.summarize_targets();
+            self.propagate_inclusions(&mut budget);
+            if !self.collapse_cycles() {
+                break;
+            }
+        }
+        self.materialize_views();
+        self.inclusions_solved = true;
+    }
+
+    /// the same target of every location behind a solved view flows into the target
+    /// made for it, which gets their alias sets
+    pub(super) fn share_solved_target(&mut self, view: Node, label: EdgeLabel, target: Node) {
+        let mut locations = vec![];
+        let mut visited = FxHashSet::default();
+        let mut stack = self.sources_of(view);
+        while let Some(node) = stack.pop() {
+            if !visited.insert(node) {
+                continue;
+            }
+            let sources = self.sources_of(node);
+            if sources.is_empty() {
+                locations.push(node);
+            } else {
+                stack.extend(sources);
+            }
+        }
+        let mut members = FxHashSet::default();
+        for location in locations {
+            unsafe {
+                let shared = self.target_or_new(&(*location).id.def_id, location, label);
+                self.add_inclusion(shared, target);
+                members.extend((*(*shared).alias_set).iter().copied());
+            }
+        }
+        if !members.is_empty() {
+            unsafe {
+                *(*target).alias_set = members;
+            }
+        }
+    }
+
+    /// the inclusions between the current representatives
+    fn inclusion_edges(&self) -> Vec<(Node, Node)> {
+        let mut edges = FxHashSet::default();
+        for (to, froms) in self.inclusions.iter() {
+            let Some(to) = self.get_node(to) else {
+                continue;
+            };
+            for from in froms.iter() {
+                match self.get_node(from) {
+                    Some(from) if from != to => {
+                        edges.insert((from, to));
+                    }
+                    _ => (),
+                }
+            }
+        }
+        edges.into_iter().collect()
+    }
+
+    /// a field is a single location, so the copies of one are merged; a pointer, guard
+    /// or future gets a single summary target, which the locations it points to flow into
+    fn summarize_targets(&mut self) {
+        unsafe {
+            let mut merged = true;
+            while merged {
+                merged = false;
+                for node in self.nodes.clone() {
+                    if !self.nodes.contains(&node) {
+                        continue;
+                    }
+                    for label in (*node).out_labels.clone() {
+                        if !label.is_part() {
+                            continue;
+                        }
+                        let targets = self.targets_of(node, &label);
+                        if let [first, rest @ ..] = &targets[..] {
+                            let mut field = *first;
+                            for other in rest {
+                                field = self.combine(field, *other);
+                                merged = true;
+                            }
+                        }
+                    }
+                }
+            }
+            for node in self.nodes.clone() {
+                for label in (*node).out_labels.clone() {
+                    if label.is_part() {
+                        continue;
+                    }
+                    let targets = self.targets_of(node, &label);
+                    match &targets[..] {
+                        [] => continue,
+                        [target] if self.is_summary(*target) => continue,
+                        _ => (),
+                    }
+                    for &target in targets.iter() {
+                        (*node).remove_target(target, &label);
+                    }
+                    let summary = self.get_or_create_target(&(*node).id.def_id, node, label);
+                    for target in targets {
+                        self.add_inclusion(target, summary);
+                    }
+                }
+            }
+        }
+    }
+
+    /// for every `from` flowing into `to`: the targets of `from` flow into the summary
+    /// target of `to`, and each field of either flows into the same field of `to`
+    fn propagate_inclusions(&mut self, budget: &mut usize) {
+        let mut outs: FxHashMap<Node, Vec<Node>> = FxHashMap::default();
+        let mut ins: FxHashMap<Node, Vec<Node>> = FxHashMap::default();
+        let mut work_list = VecDeque::new();
+        for (from, to) in self.inclusion_edges() {
+            outs.entry(from).or_default().push(to);
+            ins.entry(to).or_default().push(from);
+            work_list.push_back((from, to));
+        }
+        while let Some((from, to)) = work_list.pop_front() {
+            let mut added = vec![];
+            // the nodes given a new target, whose inclusions are visited again
+            let mut grown = vec![];
+            unsafe {
+                let labels: FxHashSet<EdgeLabel> = (*from)
+                    .out_labels
+                    .iter()
+                    .chain((*to).out_labels.iter())
+                    .copied()
+                    .collect();
+                for label in labels {
+                    if label.is_part() {
+                        let Some(from_field) = self.target_of(from, label, budget, &mut grown)
+                        else {
+                            continue;
+                        };
+                        let Some(to_field) = self.target_of(to, label, budget, &mut grown) else {
+                            continue;
+                        };
+                        if self.add_inclusion(from_field, to_field) {
+                            added.push((from_field, to_field));
+                        }
+                        continue;
+                    }
+                    let mut targets = self.targets_of(from, &label);
+                    // a location nothing flows into shares a target made for its view, e.g.
+                    // the mutex behind a parameter reached by two `raw()` calls
+                    if targets.is_empty() && !ins.contains_key(&from) && self.has_target(to, label) {
+                        targets.extend(self.target_of(from, label, budget, &mut grown));
+                    }
+                    if targets.is_empty() {
+                        continue;
+                    }
+                    let Some(summary) = self.target_of(to, label, budget, &mut grown) else {
+                        continue;
+                    };
+                    for target in targets {
+                        if self.add_inclusion(target, summary) {
+                            added.push((target, summary));
+                        }
+                    }
+                }
+            }
+            for (from, to) in added {
+                outs.entry(from).or_default().push(to);
+                ins.entry(to).or_default().push(from);
+                work_list.push_back((from, to));
+            }
+            for node in grown {
+                for &next in outs.get(&node).into_iter().flatten() {
+                    work_list.push_back((node, next));
+                }
+                for &prev in ins.get(&node).into_iter().flatten() {
+                    work_list.push_back((prev, node));
+                }
+            }
+        }
+    }
+
+    fn has_target(&self, node: Node, label: EdgeLabel) -> bool {
+        unsafe { (*node).get_out_vertex(&label).is_some() }
+    }
+
+    /// the target of `node` through `label`, created while the budget lasts
+    fn target_of(
+        &mut self,
+        node: Node,
+        label: EdgeLabel,
+        budget: &mut usize,
+        grown: &mut Vec<Node>,
+    ) -> Option<Node> {
+        unsafe {
+            if let Some(target) = (*node).get_out_vertex(&label) {
+                return Some(target);
+            }
+            if *budget == 0 {
+                return None;
+            }
+            *budget -= 1;
+            grown.push(node);
+            Some(self.get_or_create_target(&(*node).id.def_id.clone(), node, label))
+        }
+    }
+
+    /// merge the nodes flowing into each other, which see the same locations;
+    /// returns whether any were merged
+    fn collapse_cycles(&mut self) -> bool {
+        let edges = self.inclusion_edges();
+        let mut succs: FxHashMap<Node, Vec<Node>> = FxHashMap::default();
+        let mut preds: FxHashMap<Node, Vec<Node>> = FxHashMap::default();
+        for &(from, to) in edges.iter() {
+            succs.entry(from).or_default().push(to);
+            preds.entry(to).or_default().push(from);
+        }
+        // Kosaraju: the nodes by their finishing time, then the components
+        // of the reversed inclusions in the reverse of that order
+        let mut visited = FxHashSet::default();
+        let mut order = vec![];
+        for &(start, _) in edges.iter() {
+            if !visited.insert(start) {
+                continue;
+            }
+            let mut stack = vec![(start, 0)];
+            while let Some((node, index)) = stack.pop() {
+                match succs.get(&node).and_then(|next| next.get(index)) {
+                    Some(&next) => {
+                        stack.push((node, index + 1));
+                        if visited.insert(next) {
+                            stack.push((next, 0));
+                        }
+                    }
+                    None => order.push(node),
+                }
+            }
+        }
+        let mut assigned = FxHashSet::default();
+        let mut collapsed = false;
+        for &root in order.iter().rev() {
+            if !assigned.insert(root) {
+                continue;
+            }
+            let mut component = vec![root];
+            let mut stack = vec![root];
+            while let Some(node) = stack.pop() {
+                for &pred in preds.get(&node).into_iter().flatten() {
+                    if assigned.insert(pred) {
+                        component.push(pred);
+                        stack.push(pred);
+                    }
+                }
+            }
+            let mut node = root;
+            for &other in component[1..].iter() {
+                node = self.combine(node, other);
+                collapsed = true;
+            }
+        }
+        collapsed
+    }
+
+    /// the alias set of a view becomes the ones of the locations flowing into it,
+    /// which are the nodes nothing flows into
+    fn materialize_views(&mut self) {
+        let mut sources: FxHashMap<Node, Vec<Node>> = FxHashMap::default();
+        for (from, to) in self.inclusion_edges() {
+            sources.entry(to).or_default().push(from);
+        }
+        let mut locations: FxHashMap<Node, FxHashSet<Node>> = FxHashMap::default();
+        let mut visiting = FxHashSet::default();
+        for &view in sources.keys() {
+            let mut stack = vec![(view, false)];
+            while let Some((node, expanded)) = stack.pop() {
+                if locations.contains_key(&node) {
+                    continue;
+                }
+                match sources.get(&node) {
+                    None => {
+                        locations.insert(node, [node].into_iter().collect());
+                    }
+                    Some(froms) if expanded => {
+                        let behind = froms
+                            .iter()
+                            .filter_map(|from| locations.get(from))
+                            .flatten()
+                            .copied()
+                            .collect();
+                        locations.insert(node, behind);
+                    }
+                    // a cycle left by the budget is cut where it is entered again
+                    Some(_) if !visiting.insert(node) => (),
+                    Some(froms) => {
+                        stack.push((node, true));
+                        for from in froms.iter() {
+                            if !locations.contains_key(from) {
+                                stack.push((*from, false));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        unsafe {
+            let alias_sets: Vec<(Node, FxHashSet<Node>)> = sources
+                .keys()
+                .map(|view| {
+                    let members = locations[view]
+                        .iter()
+                        .flat_map(|location| (*(**location).alias_set).iter().copied())
+                        .collect();
+                    (*view, members)
+                })
+                .collect();
+            for (view, members) in alias_sets {
+                if !members.is_empty() {
+                    *(*view).alias_set = members;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustc_hir::def_id::{DefId, DefIndex};
+
+    use super::*;
+    use crate::analysis::alias::node::GraphNodeId;
+
+    #[test]
+    fn test_solve_inclusions() {
+        let mut graph = AliasGraph::new();
+        let def_id = DefId::local(DefIndex::from_u32(1));
+        let node = |graph: &mut AliasGraph, index| {
+            graph.get_or_insert_node(GraphNodeId::local(def_id, index))
+        };
+        // _3 = &_1; _4 = &_2; _5 = copy _3; _5 = copy _4; _6 = copy _3
+        let (lock1, lock2) = (node(&mut graph, 1), node(&mut graph, 2));
+        let (ref1, ref2) = (node(&mut graph, 3), node(&mut graph, 4));
+        let (either, only1) = (node(&mut graph, 5), node(&mut graph, 6));
+        unsafe {
+            (*ref1).add_target(lock1, EdgeLabel::Deref);
+            (*ref2).add_target(lock2, EdgeLabel::Deref);
+        }
+        graph.add_inclusion(ref1, either);
+        graph.add_inclusion(ref2, either);
+        graph.add_inclusion(ref1, only1);
+        // *_5 and *_6 are dereferenced after the copies
+        let deref_either = graph.get_or_create_target(&def_id, either, EdgeLabel::Deref);
+        let deref_only1 = graph.get_or_create_target(&def_id, only1, EdgeLabel::Deref);
+        graph.solve_inclusions();
+
+        let ids = |node: Node| -> FxHashSet<GraphNodeId> {
+            unsafe { (*(*node).alias_set).iter().map(|member| (**member).id.clone()).collect() }
+        };
+        let (id1, id2) = (GraphNodeId::local(def_id, 1), GraphNodeId::local(def_id, 2));
+        assert_eq!(ids(deref_either), [id1.clone(), id2.clone()].into_iter().collect());
+        assert_eq!(ids(deref_only1), [id1.clone()].into_iter().collect());
+        // the locks themselves are not merged, as they would be by unification
+        assert_ne!(graph.get_node(&id1), graph.get_node(&id2));
+    }
+}
@@ -0,0 +1,346 @@
+//! Demand-driven alias queries.
+//!
+//! Instead of solving the whole graph, a query walks back from one node along the
+//! inclusions, and from a field or a summary target to the same one of every value
+//! flowing into its parent, matching the labels like parentheses in a CFL-reachability
+//! query. The values nothing flows into are the locations the node may stand for, and
+//! two nodes may alias if they share one. Answers are cached, and a query running out
+//! of its budget answers conservatively, without being cached.
+
+use std::{collections::VecDeque, rc::Rc};
+
+use rustc_hash::{FxHashMap, FxHashSet};
+use rustc_hir::def_id::DefId;
+use rustc_middle::mir::{Place, ProjectionElem};
+
+use crate::analysis::alias::node::{AliasGraphNode, EdgeLabel, GraphNodeId};
+
+use super::AliasGraph;
+
+/// the most nodes a query visits
+const QUERY_BUDGET: usize = 10_000;
+/// the deepest field or target of a looked up node given its own locations, as deep as
+/// a guard can be found in a value
+const MAX_DEMAND_DEPTH: usize = 6;
+
+type Node = *mut AliasGraphNode;
+
+#[derive(Clone, Default)]
+pub struct AliasQuery {
+    values: FxHashMap<Node, Rc<FxHashSet<Node>>>,
+    aliases: FxHashMap<(Node, Node), bool>,
+    in_progress: FxHashSet<Node>,
+    // whether a recursion through a node in progress was cut, leaving the answer partial
+    cut: bool,
+}
+
+impl AliasQuery {
+    fn values(
+        &mut self,
+        graph: &AliasGraph,
+        node: Node,
+        steps: &mut usize,
+    ) -> Option<Rc<FxHashSet<Node>>> {
+        if let Some(values) = self.values.get(&node) {
+            return Some(values.clone());
+        }
+        if !self.in_progress.insert(node) {
+            self.cut = true;
+            return Some(Rc::default());
+        }
+        let cut = std::mem::replace(&mut self.cut, false);
+        let mut values = FxHashSet::default();
+        let mut work_list = vec![node];
+        let mut complete = true;
+        while let Some(value) = work_list.pop() {
+            if !values.insert(value) {
+                continue;
+            }
+            if *steps == 0 {
+                complete = false;
+                break;
+            }
+            *steps -= 1;
+            work_list.extend(graph.sources_of(value));
+            // a field or a summary target stands for the same one of the parent's values
+            for (parent, label) in graph.parents_of(value) {
+                let Some(parent_values) = self.values(graph, parent, steps) else {
+                    complete = false;
+                    break;
+                };
+                for &other in parent_values.iter().filter(|other| **other != parent) {
+                    work_list.extend(graph.targets_of(other, &label));
+                }
+            }
+            if !complete {
+                break;
+            }
+        }
+        self.in_progress.remove(&node);
+        if !complete {
+            self.cut = cut;
+            return None;
+        }
+        let values = Rc::new(values);
+        if !self.cut {
+            self.values.insert(node, values.clone());
+        }
+        self.cut |= cut;
+        Some(values)
+    }
+}
+
+impl AliasGraph {
+    /// whether two nodes may stand for the same location, true if the query runs out
+    /// of its budget
+    pub fn may_alias(&self, a: Node, b: Node) -> bool {
+        if a == b {
+            return true;
+        }
+        let key = if a < b { (a, b) } else { (b, a) };
+        if let Some(answer) = self.query.borrow().aliases.get(&key) {
+            return *answer;
+        }
+        let (Some(a), Some(b)) = (self.locations(a), self.locations(b)) else {
+            return true;
+        };
+        let answer = a.iter().any(|location| b.contains(location));
+        self.query.borrow_mut().aliases.insert(key, answer);
+        answer
+    }
+
+    /// the node of a place, without creating the missing ones
+    pub fn find_place(&self, def_id: &DefId, place: &Place) -> Option<Node> {
+        let mut node = self.get_node(&GraphNodeId::local(*def_id, place.local.as_usize()))?;
+        let mut variant = None;
+        for projection in place.projection {
+            let downcast = variant.take();
+            let label = match projection {
+                ProjectionElem::Deref => EdgeLabel::Deref,
+                ProjectionElem::Field(field_idx, _) => {
+                    self.field_label(def_id, downcast, field_idx.as_usize())
+                }
+                ProjectionElem::Downcast(_, variant_idx) => {
+                    variant = Some(variant_idx.as_usize());
+                    continue;
+                }
+                ProjectionElem::Index(_) | ProjectionElem::ConstantIndex { .. } => {
+                    self.index_label(def_id, node, projection)
+                }
+                ProjectionElem::Subslice { .. }
+                | ProjectionElem::OpaqueCast(_)
+                | ProjectionElem::Subtype(_) => continue,
+            };
+            node = unsafe { (*node).get_out_vertex(&label)? };
+        }
+        Some(node)
+    }
+
+    /// the locations a node may stand for, `None` if the query runs out of its budget
+    pub fn locations(&self, node: Node) -> Option<Vec<Node>> {
+        let values = self.values(node)?;
+        let mut locations = vec![];
+        for &value in values.iter() {
+            if self.values(value)?.len() == 1 {
+                locations.push(value);
+            }
+        }
+        Some(locations)
+    }
+
+    /// the nodes whose value may flow into `node`, itself included
+    fn values(&self, node: Node) -> Option<Rc<FxHashSet<Node>>> {
+        let mut query = self.query.borrow_mut();
+        query.cut = false;
+        let mut steps = QUERY_BUDGET;
+        query.values(self, node, &mut steps)
+    }
+
+    /// shortest label path from `from` to `to` in the unsolved graph of the demand-driven
+    /// mode, searched back from `to` along the edges into it and the inclusions, which
+    /// a value flows along without a label
+    pub(super) fn find_path_back(
+        &self,
+        from: Node,
+        to: Node,
+        max_depth: usize,
+    ) -> Option<Vec<EdgeLabel>> {
+        let mut visited = FxHashSet::default();
+        let mut work_list = VecDeque::new();
+        visited.insert(to);
+        work_list.push_back((to, vec![]));
+        while let Some((node, path)) = work_list.pop_front() {
+            if node == from {
+                return Some(path.into_iter().rev().collect());
+            }
+            for source in self.sources_of(node) {
+                if visited.insert(source) {
+                    work_list.push_back((source, path.clone()));
+                }
+            }
+            if path.len() >= max_depth {
+                continue;
+            }
+            unsafe {
+                let mut labels: Vec<_> = (*node).in_labels.iter().copied().collect();
+                labels.sort();
+                for label in labels {
+                    if matches!(label, EdgeLabel::Guard | EdgeLabel::Pending) {
+                        continue;
+                    }
+                    let Some(parents) = (*node).get_in_vertices(&label) else {
+                        continue;
+                    };
+                    for &parent in (*parents).iter() {
+                        if visited.insert(parent) {
+                            let mut next = path.clone();
+                            next.push(label);
+                            work_list.push_back((parent, next));
+                        }
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// the nodes `node` is a field of, or the summary target of
+    fn parents_of(&self, node: Node) -> Vec<(Node, EdgeLabel)> {
+        let summary = self.is_summary(node);
+        let mut parents = vec![];
+        unsafe {
+            for label in (*node).in_labels.iter() {
+                if !summary && !label.is_part() {
+                    continue;
+                }
+                if let Some(in_vertices) = (*node).get_in_vertices(label) {
+                    parents.extend((*in_vertices).iter().map(|parent| (*parent, *label)));
+                }
+            }
+        }
+        parents
+    }
+
+    /// answer the queries on demand from now on, as the alias analysis is done
+    pub fn set_demand_driven(&mut self) {
+        self.demand_driven = true;
+    }
+
+    /// the lock set analysis adds guards to the graph, which the cached answers miss
+    pub fn clear_queries(&mut self) {
+        *self.query.borrow_mut() = AliasQuery::default();
+        self.demanded.clear();
+    }
+
+    /// in the demand-driven mode, give a node looked up the alias set of the locations
+    /// it may stand for, and the targets and fields the values flowing into it have
+    pub(super) fn demand(&mut self, node: Node) {
+        if self.demand_driven {
+            self.demand_within(node, 0);
+        }
+    }
+
+    fn demand_within(&mut self, node: Node, depth: usize) {
+        if depth > MAX_DEMAND_DEPTH || !self.demanded.insert(node) {
+            return;
+        }
+        let Some(values) = self.values(node) else {
+            return;
+        };
+        unsafe {
+            let labels: FxHashSet<EdgeLabel> = values
+                .iter()
+                .flat_map(|value| (**value).out_labels.iter().copied())
+                .collect();
+            // a node nothing flows into is its own location, but its targets may still
+            // stand for others, e.g. the lock of a mapped guard
+            if values.len() > 1 {
+                if let Some(locations) = self.locations(node) {
+                    // a location shares a target made for its views, e.g. the mutex behind
+                    // a parameter reached by two `raw()` calls
+                    let mut created = false;
+                    for &location in locations.iter() {
+                        for label in labels.iter() {
+                            if (*location).get_out_vertex(label).is_none() {
+                                let def_id = (*location).id.def_id;
+                                self.target_or_new(&def_id, location, *label);
+                                created = true;
+                            }
+                        }
+                    }
+                    if created {
+                        self.query.borrow_mut().values.clear();
+                    }
+                    let members: FxHashSet<Node> = locations
+                        .iter()
+                        .flat_map(|location| (*(**location).alias_set).iter().copied())
+                        .collect();
+                    if !members.is_empty() {
+                        *(*node).alias_set = members;
+                    }
+                }
+            }
+            for label in labels {
+                if values.iter().all(|value| (**value).out_num_vertices(&label) == 0) {
+                    continue;
+                }
+                let def_id = (*node).id.def_id;
+                let target = self.target_or_new(&def_id, node, label);
+                self.demand_within(target, depth + 1);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustc_hir::def_id::DefIndex;
+
+    use super::*;
+
+    #[test]
+    fn test_may_alias() {
+        let mut graph = AliasGraph::new();
+        let def_id = DefId::local(DefIndex::from_u32(1));
+        let node = |graph: &mut AliasGraph, index| {
+            graph.get_or_insert_node(GraphNodeId::local(def_id, index))
+        };
+        // _3 = &_1; _4 = &_2; _5 = copy _3; _6 = copy (*_5)
+        let (lock1, lock2) = (node(&mut graph, 1), node(&mut graph, 2));
+        let (ref1, ref2, copy1) = (node(&mut graph, 3), node(&mut graph, 4), node(&mut graph, 5));
+        unsafe {
+            (*ref1).add_target(lock1, EdgeLabel::Deref);
+            (*ref2).add_target(lock2, EdgeLabel::Deref);
+        }
+        graph.add_inclusion(ref1, copy1);
+        let deref_copy1 = graph.get_or_create_target(&def_id, copy1, EdgeLabel::Deref);
+        let moved = node(&mut graph, 6);
+        graph.add_inclusion(deref_copy1, moved);
+
+        assert!(graph.may_alias(moved, lock1));
+        assert!(graph.may_alias(deref_copy1, lock1));
+        assert!(!graph.may_alias(moved, lock2));
+        assert!(!graph.may_alias(lock1, lock2));
+        assert_eq!(graph.locations(moved), Some(vec![lock1]));
+    }
+
+    #[test]
+    fn test_find_path_back() {
+        let mut graph = AliasGraph::new();
+        let def_id = DefId::local(DefIndex::from_u32(1));
+        // a closure's env _1 captures an `Arc` in its field 0, _4 = copy _1.0,
+        // and the lock is (*_4).1
+        let env = graph.get_or_insert_node(GraphNodeId::local(def_id, 1));
+        let arc = graph.get_or_create_target(&def_id, env, EdgeLabel::Field(0));
+        let copy = graph.get_or_insert_node(GraphNodeId::local(def_id, 4));
+        graph.add_inclusion(arc, copy);
+        let pair = graph.get_or_create_target(&def_id, copy, EdgeLabel::Deref);
+        let lock = graph.get_or_create_target(&def_id, pair, EdgeLabel::Field(1));
+
+        assert_eq!(graph.find_path(env, lock, 6), None);
+        graph.set_demand_driven();
+        let path = vec![EdgeLabel::Field(0), EdgeLabel::Deref, EdgeLabel::Field(1)];
+        assert_eq!(graph.find_path(env, lock, 6), Some(path));
+        assert_eq!(graph.find_path(env, lock, 2), None);
+    }
+}
@@ -1,34 +1,14 @@
 use std::{
     cell::{Ref, RefCell, RefMut},
-    fmt::Debug,
+    fmt::{Debug, Display},
     hash::Hash,
     rc::Rc,
-    sync::Mutex,
+    sync::Arc,
 };
 
 use rustc_hash::{FxHashMap, FxHashSet};
 use rustc_hir::def_id::DefId;
-
-use lazy_static::lazy_static;
-lazy_static! {
-    static ref LOCAL_INDEX: Mutex<usize> = Mutex::new(0);
-}
-
-pub fn next_local_id() -> usize {
-    let mut id = LOCAL_INDEX.lock().unwrap();
-    *id += 1;
-    *id
-}
-
-pub fn get_local_id() -> usize {
-    let id = LOCAL_INDEX.lock().unwrap();
-    *id
-}
-
-pub fn set_local_id(num: usize) {
-    let mut id = LOCAL_INDEX.lock().unwrap();
-    *id = num;
-}
+use serde::{Deserialize, Serialize};
 
 pub struct AliasGraphNode {
     pub id: GraphNodeId,
@@ -310,35 +290,110 @@ impl AliasGraphNode {
     }
 }
 
-/// 1. a local's node index is its local index in mir
-/// 2. a temp's node index is started from the number of locals
-/// i.e., if there's 10 locals in a function, then:
-/// assign _0 = _1.field_0.field1 ==>
-/// _1 --field--> _10 --field--> _11, than make_alias(_0, _11)
-#[derive(Debug, Clone, Hash, PartialEq, Eq, Copy)]
+/// a node of a function: a local, by its index in mir, or a node the analyses synthesize,
+/// by what it stands for, see `NodeKey`; `nth` tells apart the nodes synthesized for the
+/// same key, e.g. a target made again for an edge a merge took away. As it only depends on
+/// the key, the same node is named the same whichever order the nodes are made in
+/// i.e. assign _0 = _1.field_0.field1 ==>
+/// _1 --field--> (_1).0 --field--> ((_1).0).1, than make_alias(_0, ((_1).0).1)
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub struct GraphNodeId {
     pub def_id: DefId,
-    pub index: usize,
+    pub key: NodeKey,
+    pub nth: usize,
 }
 
 impl GraphNodeId {
-    pub fn new(def_id: DefId, index: Option<usize>) -> Self {
-        GraphNodeId {
-            def_id,
-            index: match index {
-                Some(i) => i,
-                None => next_local_id(),
+    pub fn new(def_id: DefId, key: NodeKey) -> Self {
+        GraphNodeId { def_id, key, nth: 0 }
+    }
+
+    /// the node of a local of `def_id`
+    pub fn local(def_id: DefId, index: usize) -> Self {
+        GraphNodeId::new(def_id, NodeKey::Local(index))
+    }
+}
+
+// `DefId` has no order of its own, its crate and index do
+impl Ord for GraphNodeId {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.def_id.krate, self.def_id.index, &self.key, self.nth).cmp(&(
+            other.def_id.krate,
+            other.def_id.index,
+            &other.key,
+            other.nth,
+        ))
+    }
+}
+
+impl PartialOrd for GraphNodeId {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for GraphNodeId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.key)?;
+        if self.nth > 0 {
+            write!(f, "#{}", self.nth)?;
+        }
+        Ok(())
+    }
+}
+
+/// what a node stands for in its function
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum NodeKey {
+    /// a local, by its index in mir
+    Local(usize),
+    /// the target of `base` through `label`, e.g. `(*_1)` of `_1 --deref--> (*_1)`
+    Target { base: Arc<GraphNodeId>, label: EdgeLabel },
+    /// a value the call terminating `block` creates
+    Call { block: usize, role: CallRole },
+    /// the copy of a callee's `node` made for the call terminating `block`
+    Copy { block: usize, node: Arc<GraphNodeId> },
+    /// the values the analyses cannot tell the origin of, one node for the whole crate
+    Unknown,
+}
+
+impl Display for NodeKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NodeKey::Local(index) => write!(f, "_{}", index),
+            NodeKey::Target { base, label } => match label {
+                EdgeLabel::Deref => write!(f, "(*{})", base),
+                EdgeLabel::Guard => write!(f, "{}.guard", base),
+                EdgeLabel::Pending => write!(f, "{}.pending", base),
+                EdgeLabel::Field(field) => write!(f, "{}.{}", base, field),
+                EdgeLabel::Element(offset) => write!(f, "{}[{}]", base, offset),
+                EdgeLabel::Index => write!(f, "{}[_]", base),
             },
+            NodeKey::Call { block, role } => write!(f, "{:?} of bb{}", role, block),
+            NodeKey::Copy { block, node } => write!(f, "{} copied for bb{}", node, block),
+            NodeKey::Unknown => write!(f, "unknown"),
         }
     }
 }
 
-#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CallRole {
+    /// the channel both endpoints of `channel()` point to
+    Channel,
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize)]
 pub enum EdgeLabel {
     Deref,
     Guard,
-    // todo: field, array access
+    /// from the future of an async lock to the lock
+    Pending,
     Field(usize),
+    /// from an array to its element at a constant offset from the start
+    Element(usize),
+    /// from an array or a slice to the elements indexed by a value the analysis cannot
+    /// tell, all of which one node stands for
+    Index,
 }
 
 impl From<&str> for EdgeLabel {
@@ -346,6 +401,8 @@ impl From<&str> for EdgeLabel {
         match label {
             "Deref" => EdgeLabel::Deref,
             "Guard" => EdgeLabel::Guard,
+            "Pending" => EdgeLabel::Pending,
+            "Index" => EdgeLabel::Index,
             _ => panic!("Unknown edge label!"),
         }
     }
@@ -354,4 +411,9 @@ impl EdgeLabel {
     pub fn new_field(field_idx: usize) -> Self {
         EdgeLabel::Field(field_idx)
     }
+
+    /// whether the target is a part of the node's location, rather than one it points to
+    pub fn is_part(&self) -> bool {
+        matches!(self, EdgeLabel::Field(_) | EdgeLabel::Element(_) | EdgeLabel::Index)
+    }
 }
@@ -0,0 +1,75 @@
+use rustc_hir::def_id::DefId;
+use rustc_middle::mir::Location;
+
+use super::lock::Lock;
+
+type StatementSite = (DefId, Location);
+
+/// a `Ref` or `RefMut` alive at a program point
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeldBorrow {
+    pub cell: Lock,
+    pub mutable: bool,
+    pub site: StatementSite,
+}
+
+/// a `RefCell::borrow` or `borrow_mut`, and the borrows alive when it happens
+#[derive(Debug, Clone)]
+pub struct BorrowOp {
+    pub mutable: bool,
+    /// where the cell is borrowed, in the original function
+    pub site: StatementSite,
+    /// the function in whose terms `cell` and `held` are
+    pub owner: DefId,
+    /// the call in `owner` reaching the borrow, when copied into a caller
+    pub via: Option<StatementSite>,
+    pub cell: Vec<Lock>,
+    pub held: Vec<HeldBorrow>,
+}
+
+impl BorrowOp {
+    /// the held borrows of the same cell that make this borrow panic
+    pub fn conflicts(&self) -> impl Iterator<Item = &HeldBorrow> {
+        self.held
+            .iter()
+            .filter(|held| (self.mutable || held.mutable) && self.cell.contains(&held.cell))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustc_hir::def_id::{CrateNum, DefIndex};
+    use rustc_middle::mir::BasicBlock;
+
+    use super::*;
+    use crate::analysis::alias::node::GraphNodeId;
+
+    #[test]
+    fn test_conflicts() {
+        let def_id = DefId {
+            index: DefIndex::from_u32(0),
+            krate: CrateNum::from_u32(0),
+        };
+        let site = (def_id, Location::START);
+        let cell = Lock::new(GraphNodeId::local(def_id, 1));
+        let other = Lock::new(GraphNodeId::local(def_id, 2));
+        let held = |cell: &Lock, mutable| HeldBorrow {
+            cell: cell.clone(),
+            mutable,
+            site: (def_id, BasicBlock::from_u32(1).start_location()),
+        };
+        let op = |mutable, held| BorrowOp {
+            mutable,
+            site,
+            owner: def_id,
+            via: None,
+            cell: vec![cell.clone()],
+            held,
+        };
+
+        assert_eq!(op(false, vec![held(&cell, false)]).conflicts().count(), 0);
+        assert_eq!(op(false, vec![held(&cell, true)]).conflicts().count(), 1);
+        assert_eq!(op(true, vec![held(&cell, false)]).conflicts().count(), 1);
+        assert_eq!(op(true, vec![held(&other, true)]).conflicts().count(), 0);
+    }
+}
@@ -3,12 +3,13 @@ use collector::FnCollector;
 use rustc_hash::{FxHashMap, FxHashSet};
 
 use rustc_hir::{def_id::DefId, intravisit::Visitor, BodyId, HirId, ItemKind};
-use rustc_middle::mir::{Location, Operand, TerminatorKind};
+use rustc_middle::mir::{
+    AggregateKind, Location, Operand, Rvalue, StatementKind, TerminatorKind,
+};
 use rustc_middle::ty::{self, TyCtxt};
 use rustc_span::Span;
 
 use crate::context::MyTcx;
-use crate::driver::AnalysisPass;
 
 pub mod call_graph_node;
 pub mod collector;
@@ -73,6 +74,22 @@ impl<'a, 'tcx> CallGraphPass<'a, 'tcx> {
         if tcx.is_mir_available(def_id) {
             let body = tcx.optimized_mir(def_id);
             for bb in body.basic_blocks.iter() {
+                // a closure or a coroutine is analyzed before the function creating it,
+                // the same way as a callee
+                for statement in bb.statements.iter() {
+                    if let StatementKind::Assign(assign) = &statement.kind {
+                        if let Rvalue::Aggregate(kind, _) = &assign.1 {
+                            if let AggregateKind::Closure(closure, _)
+                            | AggregateKind::Coroutine(closure, _) = **kind
+                            {
+                                self.my_tcx.call_graph.edges.insert((def_id, closure));
+                                if self.my_tcx.call_graph.fn_set.insert(closure) {
+                                    self.find_callees(closure);
+                                }
+                            }
+                        }
+                    }
+                }
                 match &bb.terminator().kind {
                     TerminatorKind::Call { func, args, .. } => {
                         if let Operand::Constant(func_constant) = func {
@@ -182,20 +199,54 @@ impl<'tcx> CallGraph<'tcx> {
             fn_set: FxHashSet::default(),
         }
     }
-}
 
-impl<'a, 'tcx> AnalysisPass for CallGraphPass<'a, 'tcx> {
-    fn name(&self) -> String {
-        "[Call Graph pre build]".to_string()
+    /// the functions of `topo` by layers: a function is one layer above its highest
+    /// callee, so the functions of a layer never call each other, except through a
+    /// recursion, which is cut where `topo` visits it first
+    pub fn layers(&self) -> Vec<Vec<DefId>> {
+        let mut callees: FxHashMap<DefId, Vec<DefId>> = FxHashMap::default();
+        for (caller, callee) in self.edges.iter() {
+            callees.entry(*caller).or_default().push(*callee);
+        }
+        let mut depths: FxHashMap<DefId, usize> = FxHashMap::default();
+        let mut layers: Vec<Vec<DefId>> = vec![];
+        for def_id in self.topo.iter() {
+            let depth = callees
+                .get(def_id)
+                .into_iter()
+                .flatten()
+                .filter_map(|callee| depths.get(callee))
+                .map(|depth| depth + 1)
+                .max()
+                .unwrap_or(0);
+            depths.insert(*def_id, depth);
+            if layers.len() <= depth {
+                layers.resize(depth + 1, vec![]);
+            }
+            layers[depth].push(*def_id);
+        }
+        layers
     }
+}
 
-    fn before_run(&mut self) {
-        tracing::info!("{} analysis is running.", self.name());
-    }
+#[cfg(test)]
+mod tests {
+    use rustc_hir::def_id::DefIndex;
 
-    fn run_pass(&mut self) {
-        self.start();
-    }
+    use super::*;
 
-    fn after_run(&mut self) {}
+    #[test]
+    fn test_layers() {
+        let def_id = |index| DefId::local(DefIndex::from_u32(index));
+        let mut call_graph = CallGraph::new();
+        // 1 -> 2 -> 3, 1 -> 3, 4 -> 3, and 3 calls itself
+        for (caller, callee) in [(1, 2), (2, 3), (1, 3), (4, 3), (3, 3)] {
+            call_graph.edges.insert((def_id(caller), def_id(callee)));
+        }
+        call_graph.topo = vec![def_id(3), def_id(2), def_id(4), def_id(1)];
+        assert_eq!(
+            call_graph.layers(),
+            vec![vec![def_id(3)], vec![def_id(2), def_id(4)], vec![def_id(1)]]
+        );
+    }
 }
@@ -0,0 +1,38 @@
+use rustc_hir::def_id::DefId;
+use rustc_middle::mir::Location;
+
+use super::lock::Lock;
+
+type StatementSite = (DefId, Location);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CondvarOpKind {
+    Wait,
+    /// waits with a predicate re-checked by the callee, `wait_while` and `wait_timeout_while`
+    WaitWhile,
+    WaitTimeout,
+    NotifyOne,
+    NotifyAll,
+}
+
+impl CondvarOpKind {
+    pub fn is_wait(&self) -> bool {
+        matches!(
+            self,
+            CondvarOpKind::Wait | CondvarOpKind::WaitWhile | CondvarOpKind::WaitTimeout
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CondvarOp {
+    pub kind: CondvarOpKind,
+    pub site: StatementSite,
+    /// the function in whose terms `condvar` and `mutex` are
+    pub owner: DefId,
+    pub condvar: Vec<Lock>,
+    /// the mutex of the guard passed to a wait
+    pub mutex: Vec<Lock>,
+    /// the operation this one is a copy of, when copied into a caller or a spawner
+    pub origin: Option<usize>,
+}
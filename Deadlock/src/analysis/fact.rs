@@ -45,4 +45,50 @@ impl VecFact<LockSetFact> for LockSummary{
             self.extend(other[min_len..].iter().cloned());
         }
     }
-}
\ No newline at end of file
+}
+
+/// the lock set at the entry of a bb, a lock is held if it is held at the end
+/// of any of its predecessors
+pub fn join(pres: &[LockSummary]) -> LockSummary {
+    let mut joined = LockSummary::new();
+    for pre in pres {
+        joined.meet(pre);
+    }
+    joined
+}
+
+#[cfg(test)]
+mod tests {
+    use rustc_hir::def_id::{DefId, DefIndex};
+    use rustc_middle::mir::{BasicBlock, Location};
+
+    use super::*;
+    use crate::analysis::alias::node::GraphNodeId;
+    use crate::analysis::lock::Lock;
+
+    fn acquired(lock: usize, block: u32) -> LockSetFact {
+        let def_id = DefId::local(DefIndex::from_u32(1));
+        let location = Location {
+            block: BasicBlock::from_u32(block),
+            statement_index: 0,
+        };
+        LockSetFact::from_iter([LockFact {
+            is_acquisition: true,
+            state: false,
+            s_location: (def_id, location),
+            lock: Lock::new(GraphNodeId::local(def_id, lock)),
+        }])
+    }
+
+    #[test]
+    fn test_join() {
+        // a lock acquired on one branch only, and one acquired on both
+        let then_branch = vec![acquired(1, 0), acquired(2, 1)];
+        let else_branch = vec![acquired(1, 0)];
+        let joined = join(&[then_branch.clone(), else_branch.clone()]);
+        assert_eq!(joined, then_branch);
+        // whichever predecessor comes last
+        assert_eq!(join(&[else_branch, then_branch.clone()]), then_branch);
+        assert!(join(&[]).is_empty());
+    }
+}
@@ -0,0 +1,85 @@
+//! Per-function isolation of analysis failures.
+//!
+//! An unexpected MIR shape may trip an assertion deep in an analysis. Rather than
+//! losing the whole crate to it, each function is analyzed under `catch_unwind`; a
+//! function that panics is skipped with the panic's message and location, and its
+//! summary is left unknown. The panic hook is swapped for the duration, so rustc's
+//! ICE report is not printed for a failure that is caught; as the hook is global,
+//! one thread swaps it at a time, and a nested call keeps the one already swapped in.
+
+use std::{
+    cell::{Cell, RefCell},
+    panic::{self, AssertUnwindSafe},
+    sync::{Mutex, PoisonError},
+};
+
+use rustc_hir::def_id::DefId;
+
+/// a function an analysis gave up on
+#[derive(Debug, Clone)]
+pub struct SkippedFn {
+    pub def_id: DefId,
+    pub analysis: &'static str,
+    pub reason: String,
+}
+
+/// held while the panic hook is swapped
+static HOOK: Mutex<()> = Mutex::new(());
+
+thread_local! {
+    /// the message and location of the last panic caught on this thread
+    static LAST_PANIC: RefCell<Option<String>> = const { RefCell::new(None) };
+    /// whether this thread runs in an `isolate` already
+    static ISOLATED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// run `f`, turning a panic into the reason it failed
+pub fn isolate<R>(f: impl FnOnce() -> R) -> Result<R, String> {
+    if ISOLATED.get() {
+        return panic::catch_unwind(AssertUnwindSafe(f)).map_err(|_| last_panic());
+    }
+    let _swapping = HOOK.lock().unwrap_or_else(PoisonError::into_inner);
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|info| {
+        let message = match info.payload().downcast_ref::<&str>() {
+            Some(message) => message.to_string(),
+            None => match info.payload().downcast_ref::<String>() {
+                Some(message) => message.clone(),
+                None => "unknown panic".to_string(),
+            },
+        };
+        let reason = match info.location() {
+            Some(location) => format!("{} at {}", message, location),
+            None => message,
+        };
+        LAST_PANIC.with(|last| *last.borrow_mut() = Some(reason));
+    }));
+    ISOLATED.set(true);
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    ISOLATED.set(false);
+    panic::set_hook(hook);
+    result.map_err(|_| last_panic())
+}
+
+fn last_panic() -> String {
+    LAST_PANIC
+        .with(|last| last.borrow_mut().take())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_isolate() {
+        assert_eq!(isolate(|| 1), Ok(1));
+        let reason = isolate(|| -> usize { panic!("should not go to this branch!") });
+        let reason = reason.unwrap_err();
+        assert!(reason.starts_with("should not go to this branch! at "));
+        assert!(reason.contains("isolate.rs"));
+        // a nested call catches its own panic under the hook swapped in already
+        let nested = isolate(|| isolate(|| -> usize { panic!("inner") }));
+        assert!(nested.unwrap().unwrap_err().starts_with("inner at "));
+    }
+}
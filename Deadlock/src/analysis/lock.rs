@@ -4,6 +4,9 @@ use std::hash::{Hash, Hasher};
 use rustc_hash::{FxHashMap, FxHashSet};
 use rustc_hir::def_id::DefId;
 use rustc_middle::mir::Location;
+use rustc_middle::ty::TyCtxt;
+
+use super::alias::node::GraphNodeId;
 
 type StatementSite = (DefId, Location);
 
@@ -19,18 +22,24 @@ pub type LockSetFact = FxHashSet<LockFact>;
 pub type LockSummary = Vec<LockSetFact>;
 
 
-#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[derive(Debug, Clone, Eq, PartialEq, Hash, PartialOrd, Ord)]
 pub struct Lock {
-    pub(crate) def_id: DefId,
-    pub index: usize,
+    /// the alias node standing for the lock
+    pub(crate) id: GraphNodeId,
 }
 
 impl Lock{
-    pub fn new(def_id: DefId, index: usize) -> Self{
-        Lock { 
-            def_id,
-            index 
-        }
+    pub fn new(id: GraphNodeId) -> Self{
+        Lock { id }
+    }
+
+    /// the function the lock's node belongs to
+    pub fn def_id(&self) -> DefId {
+        self.id.def_id
+    }
+
+    pub fn describe(&self, tcx: TyCtxt<'_>) -> String {
+        format!("lock `{}` in {}", self.id, tcx.def_path_str(self.id.def_id))
     }
 }
 
@@ -2,15 +2,23 @@ use std::rc::Rc;
 
 use rustc_hir::def_id::DefId;
 use rustc_hash::{FxHashMap, FxHashSet};
+use rustc_middle::ty::TyCtxt;
+
+use crate::report::{Finding, Reporter};
 
 use super::lock::Lock;
 
 
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct LockGraph {
     adjacency_list: FxHashMap<Lock, Vec<Lock>>,
     self_loops: FxHashSet<Lock>,
+    // nodes standing for channels rather than locks
+    channels: FxHashSet<Lock>,
+    bounded_channels: FxHashSet<Lock>,
+    // nodes standing for any element of a lock array, which may be another lock each time
+    may_alias: FxHashSet<Lock>,
 }
 
 impl LockGraph {
@@ -18,6 +26,40 @@ impl LockGraph {
         Self {
             adjacency_list: FxHashMap::default(),
             self_loops: FxHashSet::default(),
+            channels: FxHashSet::default(),
+            bounded_channels: FxHashSet::default(),
+            may_alias: FxHashSet::default(),
+        }
+    }
+
+    pub fn add_channel(&mut self, channel: Lock, bounded: bool) {
+        if bounded {
+            self.bounded_channels.insert(channel.clone());
+        }
+        self.channels.insert(channel);
+    }
+
+    pub fn is_channel(&self, node: &Lock) -> bool {
+        self.channels.contains(node)
+    }
+
+    pub fn is_bounded(&self, channel: &Lock) -> bool {
+        self.bounded_channels.contains(channel)
+    }
+
+    pub fn add_may_alias(&mut self, lock: Lock) {
+        self.may_alias.insert(lock);
+    }
+
+    pub fn is_may_alias(&self, lock: &Lock) -> bool {
+        self.may_alias.contains(lock)
+    }
+
+    pub fn describe(&self, tcx: TyCtxt<'_>, node: &Lock) -> String {
+        if self.is_channel(node) {
+            format!("channel `{}` in {}", node.id, tcx.def_path_str(node.def_id()))
+        } else {
+            node.describe(tcx)
         }
     }
 
@@ -44,6 +86,75 @@ impl LockGraph {
     }
 
 
+    pub fn report_cycles(&self, tcx: TyCtxt<'_>, reporter: &mut Reporter) {
+        // two cycles of different nodes may still go through the same sites
+        let mut reported = FxHashSet::default();
+        for cycle in self.unique_cycles() {
+            let trace: Vec<String> = cycle.iter().map(|node| self.describe(tcx, node)).collect();
+            if !reported.insert(trace.clone()) {
+                continue;
+            }
+            let mut finding = if cycle.iter().any(|node| self.is_channel(node)) {
+                Finding::new(
+                    "channel",
+                    format!(
+                        "possible deadlock: {} locks and channels wait for each other in a cycle",
+                        cycle.len()
+                    ),
+                )
+            } else {
+                Finding::new(
+                    "lock-order",
+                    format!(
+                        "possible deadlock: {} locks are acquired in a cyclic order",
+                        cycle.len()
+                    ),
+                )
+            };
+            for (node, item) in cycle.iter().zip(trace) {
+                finding = finding.within(node.def_id()).with_trace(item);
+            }
+            reporter.report(finding);
+        }
+    }
+
+    pub fn report_self_loops(&self, tcx: TyCtxt<'_>, reporter: &mut Reporter) {
+        // acquiring an array's element twice may take two different locks
+        let mut locks: Vec<&Lock> =
+            self.self_loops.iter().filter(|lock| !self.is_may_alias(lock)).collect();
+        locks.sort();
+        let mut reported = FxHashSet::default();
+        for lock in locks {
+            let site = lock.describe(tcx);
+            if !reported.insert(site.clone()) {
+                continue;
+            }
+            reporter.report(
+                Finding::new("double-lock", String::from("possible double lock"))
+                    .within(lock.def_id())
+                    .with_trace(site),
+            );
+        }
+    }
+
+    /// every cycle once, starting from its least lock: the search finds a cycle again
+    /// through an edge added twice
+    pub fn unique_cycles(&self) -> Vec<Vec<Lock>> {
+        let mut cycles: Vec<Vec<Lock>> = self
+            .find_all_cycles()
+            .into_iter()
+            .map(|mut cycle| {
+                if let Some((start, _)) = cycle.iter().enumerate().min_by_key(|(_, lock)| *lock) {
+                    cycle.rotate_left(start);
+                }
+                cycle
+            })
+            .collect();
+        cycles.sort();
+        cycles.dedup();
+        cycles
+    }
+
     pub fn find_all_cycles(&self) -> Vec<Vec<Lock>> {
         let mut cycles: Vec<Vec<Lock>> = Vec::new();
         let mut stack: Vec<Lock> = Vec::new();
@@ -103,15 +214,16 @@ mod test{
     use rustc_hir::def_id::{CrateNum, DefIndex};
 
     use super::*;
+    use crate::analysis::alias::node::GraphNodeId;
 
 
     #[test]
     fn test_graph() {
         let mut graph = LockGraph::new();
-        let lock0 = Lock::new(DefId{ index: DefIndex::from_u32(0), krate: CrateNum::from_u32(1) }, 0);
-        let lock1 = Lock::new(DefId{ index: DefIndex::from_u32(1), krate: CrateNum::from_u32(2) }, 1);
-        let lock2 = Lock::new(DefId{ index: DefIndex::from_u32(2), krate: CrateNum::from_u32(3) }, 2);
-        let lock3 = Lock::new(DefId{ index: DefIndex::from_u32(3), krate: CrateNum::from_u32(4) }, 3);
+        let lock0 = Lock::new(GraphNodeId::local(DefId{ index: DefIndex::from_u32(0), krate: CrateNum::from_u32(1) }, 0));
+        let lock1 = Lock::new(GraphNodeId::local(DefId{ index: DefIndex::from_u32(1), krate: CrateNum::from_u32(2) }, 1));
+        let lock2 = Lock::new(GraphNodeId::local(DefId{ index: DefIndex::from_u32(2), krate: CrateNum::from_u32(3) }, 2));
+        let lock3 = Lock::new(GraphNodeId::local(DefId{ index: DefIndex::from_u32(3), krate: CrateNum::from_u32(4) }, 3));
 
         graph.add_edge(lock0.clone(), lock1.clone());
         graph.add_edge(lock1.clone(), lock2.clone());
@@ -132,4 +244,22 @@ mod test{
             println!("self loop: {:?}",se_lo);
         }
     }
+
+    #[test]
+    fn test_unique_cycles() {
+        let mut graph = LockGraph::new();
+        let def_id = DefId::local(DefIndex::from_u32(1));
+        let lock0 = Lock::new(GraphNodeId::local(def_id, 0));
+        let lock1 = Lock::new(GraphNodeId::local(def_id, 1));
+
+        // 1 -> 0 is added once for each of two acquisitions
+        graph.add_edge(lock1.clone(), lock0.clone());
+        graph.add_edge(lock0.clone(), lock1.clone());
+        graph.add_edge(lock0.clone(), lock1.clone());
+        graph.add_edge(lock1.clone(), lock0.clone());
+        graph.add_edge(lock1.clone(), lock0.clone());
+
+        assert!(graph.find_all_cycles().len() > 1);
+        assert_eq!(graph.unique_cycles(), vec![vec![lock0, lock1]]);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,224 @@
+//! Branch conditions for the path-sensitive mode.
+//!
+//! A lock fact remembers the conditions holding on every path to its site, and
+//! is dropped on the edges of a later branch contradicting them, e.g. the guard
+//! of `if cond { Some(m.lock()) } else { None }` on the `!cond` arm of a second
+//! `if cond`. Only locals that cannot change between the two branches are used.
+
+use rustc_hash::FxHashSet;
+use rustc_middle::{
+    mir::{BasicBlock, Body, Local, Operand, Rvalue, StatementKind, TerminatorKind, UnOp},
+    ty::TyCtxt,
+};
+
+/// the most conditions remembered on a path, the earliest ones are kept
+pub const MAX_PATH_CONDITIONS: usize = 8;
+
+/// `local == value` or `local != value` on a branch edge,
+/// or the same of the local's discriminant
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Condition {
+    pub local: Local,
+    pub discriminant: bool,
+    pub value: u128,
+    pub equal: bool,
+}
+
+impl Condition {
+    pub fn contradicts(&self, other: &Condition) -> bool {
+        if self.local != other.local || self.discriminant != other.discriminant {
+            return false;
+        }
+        match (self.equal, other.equal) {
+            (true, true) => self.value != other.value,
+            (true, false) | (false, true) => self.value == other.value,
+            (false, false) => false,
+        }
+    }
+}
+
+/// whether two sets of conditions cannot hold on the same path
+pub fn contradicts(conditions: &[Condition], others: &[Condition]) -> bool {
+    conditions
+        .iter()
+        .any(|condition| others.iter().any(|other| condition.contradicts(other)))
+}
+
+/// the conditions holding on both paths
+pub fn intersect(conditions: &[Condition], others: &[Condition]) -> Vec<Condition> {
+    conditions
+        .iter()
+        .filter(|condition| others.contains(condition))
+        .cloned()
+        .collect()
+}
+
+/// the conditions on the edge `pre -> bb`, if `pre` ends with a `switchInt`
+/// and `bb` is reached for one value or for the otherwise target only
+pub fn edge_conditions<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    body: &Body<'tcx>,
+    stable: &FxHashSet<Local>,
+    pre: BasicBlock,
+    bb: BasicBlock,
+) -> Vec<Condition> {
+    let data = &body.basic_blocks[pre];
+    let TerminatorKind::SwitchInt { discr, targets } = &data.terminator().kind else {
+        return vec![];
+    };
+    let Some(discr) = discr.place().filter(|place| place.projection.is_empty()) else {
+        return vec![];
+    };
+    let is_bool = discr.ty(body, tcx).ty.is_bool();
+    // _d = copy _c; _d = !_c; _d = discriminant(_c); switchInt(move _d)
+    let mut key = (discr.local, false, false);
+    for statement in data.statements.iter().rev() {
+        let StatementKind::Assign(assign) = &statement.kind else {
+            continue;
+        };
+        if assign.0 != discr {
+            continue;
+        }
+        key = match &assign.1 {
+            Rvalue::Use(Operand::Copy(place) | Operand::Move(place))
+                if place.projection.is_empty() =>
+            {
+                (place.local, false, false)
+            }
+            Rvalue::UnaryOp(UnOp::Not, Operand::Copy(place) | Operand::Move(place))
+                if is_bool && place.projection.is_empty() =>
+            {
+                (place.local, false, true)
+            }
+            Rvalue::Discriminant(place) if place.projection.is_empty() => {
+                (place.local, true, false)
+            }
+            _ => key,
+        };
+        break;
+    }
+    let (local, discriminant, negated) = key;
+    if !stable.contains(&local) {
+        return vec![];
+    }
+    let values: Vec<u128> = targets
+        .iter()
+        .filter(|(_, target)| *target == bb)
+        .map(|(value, _)| value)
+        .collect();
+    let mut conditions: Vec<(u128, bool)> = match (targets.otherwise() == bb, &values[..]) {
+        (false, [value]) => vec![(*value, true)],
+        (true, []) => targets.iter().map(|(value, _)| (value, false)).collect(),
+        _ => vec![],
+    };
+    if is_bool {
+        // a bool is 0 or 1, so `!= 0` is `== 1`, and `!c == v` is `c == 1 - v`
+        for (value, equal) in conditions.iter_mut() {
+            if !*equal {
+                *value = 1 - *value;
+                *equal = true;
+            }
+            if negated {
+                *value = 1 - *value;
+            }
+        }
+    }
+    conditions
+        .into_iter()
+        .map(|(value, equal)| Condition {
+            local,
+            discriminant,
+            value,
+            equal,
+        })
+        .collect()
+}
+
+/// locals assigned at most once outside a loop and never borrowed mutably,
+/// which keep their value between two branches on them
+pub fn stable_locals(body: &Body<'_>) -> FxHashSet<Local> {
+    let mut defs = vec![0usize; body.local_decls.len()];
+    let mut def_blocks = vec![None; body.local_decls.len()];
+    for arg in body.args_iter() {
+        defs[arg.as_usize()] += 1;
+    }
+    for (bb, data) in body.basic_blocks.iter_enumerated() {
+        let mut define = |local: Local| {
+            defs[local.as_usize()] += 1;
+            def_blocks[local.as_usize()] = Some(bb);
+        };
+        for statement in data.statements.iter() {
+            match &statement.kind {
+                StatementKind::Assign(assign) => {
+                    define(assign.0.local);
+                    match &assign.1 {
+                        Rvalue::Ref(_, kind, place) if kind.mutability().is_mut() => {
+                            define(place.local)
+                        }
+                        Rvalue::AddressOf(mutability, place) if mutability.is_mut() => {
+                            define(place.local)
+                        }
+                        _ => {}
+                    }
+                }
+                StatementKind::SetDiscriminant { place, .. } => define(place.local),
+                _ => {}
+            }
+        }
+        if let TerminatorKind::Call { destination, .. } = &data.terminator().kind {
+            define(destination.local);
+        }
+    }
+    body.local_decls
+        .indices()
+        .filter(|local| {
+            defs[local.as_usize()] <= 1
+                && def_blocks[local.as_usize()].map_or(true, |bb| !in_cycle(body, bb))
+        })
+        .collect()
+}
+
+/// whether a bb can reach itself
+fn in_cycle(body: &Body<'_>, bb: BasicBlock) -> bool {
+    let mut visited = FxHashSet::default();
+    let mut stack: Vec<BasicBlock> = body.basic_blocks[bb].terminator().successors().collect();
+    while let Some(next) = stack.pop() {
+        if next == bb {
+            return true;
+        }
+        if visited.insert(next) {
+            stack.extend(body.basic_blocks[next].terminator().successors());
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn condition(local: u32, value: u128, equal: bool) -> Condition {
+        Condition {
+            local: Local::from_u32(local),
+            discriminant: false,
+            value,
+            equal,
+        }
+    }
+
+    #[test]
+    fn test_contradicts() {
+        let cond = condition(1, 1, true);
+        assert!(contradicts(&[cond], &[condition(1, 0, true)]));
+        assert!(contradicts(&[cond], &[condition(1, 1, false)]));
+        assert!(!contradicts(&[cond], &[condition(2, 0, true)]));
+        assert!(!contradicts(&[condition(1, 0, false)], &[condition(1, 1, false)]));
+
+        let discriminant = Condition {
+            discriminant: true,
+            ..condition(1, 0, true)
+        };
+        assert!(!contradicts(&[cond], &[discriminant]));
+        assert_eq!(intersect(&[cond, discriminant], &[discriminant]), vec![discriminant]);
+    }
+}
@@ -0,0 +1,124 @@
+//! Per-function facts computed ahead of the analyses, in parallel.
+//!
+//! The alias and the lock set analyses share graphs of raw pointers, so they run on
+//! one thread; what they need from a body alone, the order of its bbs and the locals
+//! its branches correlate on, is computed beforehand, one call graph layer after
+//! another, for all functions of a layer at once on rayon's threads, and merged once
+//! the layer is done. The bodies are fetched on the calling thread, as the `TyCtxt`
+//! cannot be shared between threads; only the bodies and plain data cross them.
+
+use rayon::prelude::*;
+use rustc_hash::{FxHashMap, FxHashSet};
+use rustc_hir::def_id::DefId;
+use rustc_middle::{
+    mir::{BasicBlock, Body, Local},
+    ty::TyCtxt,
+};
+
+use crate::option::Options;
+
+use super::{callgraph::CallGraph, path::stable_locals};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FnPrepass {
+    /// the bbs in a reverse post-order
+    pub order: Vec<BasicBlock>,
+    /// the locals the path-sensitive mode tracks branches on, empty in other modes
+    pub stable_locals: FxHashSet<Local>,
+}
+
+pub fn run_prepass<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    call_graph: &CallGraph<'tcx>,
+    options: &Options,
+) -> FxHashMap<DefId, FnPrepass> {
+    let unwind_aware = options.unwind_aware;
+    let path_sensitive = options.path_sensitive;
+    let body_of = |def_id: DefId| {
+        (def_id.is_local() && tcx.is_mir_available(def_id)).then(|| tcx.optimized_mir(def_id))
+    };
+    map_layers(&call_graph.layers(), body_of, |body: &&Body<'tcx>| FnPrepass {
+        order: bb_order(body, unwind_aware),
+        stable_locals: if path_sensitive {
+            stable_locals(body)
+        } else {
+            FxHashSet::default()
+        },
+    })
+}
+
+/// the facts of each function's body, one layer after another, with the bodies of a
+/// layer mapped in parallel; the functions `body_of` gives no body have no facts
+pub fn map_layers<B: Sync, T: Send>(
+    layers: &[Vec<DefId>],
+    mut body_of: impl FnMut(DefId) -> Option<B>,
+    facts: impl Fn(&B) -> T + Sync,
+) -> FxHashMap<DefId, T> {
+    let mut results = FxHashMap::default();
+    for layer in layers {
+        let bodies: Vec<(DefId, B)> = layer
+            .iter()
+            .filter_map(|def_id| Some((*def_id, body_of(*def_id)?)))
+            .collect();
+        let layer_results: Vec<(DefId, T)> = bodies
+            .par_iter()
+            .map(|(def_id, body)| (*def_id, facts(body)))
+            .collect();
+        results.extend(layer_results);
+    }
+    results
+}
+
+/// the bbs in a reverse post-order, cleanup blocks are only followed in the unwind-aware mode
+pub fn bb_order(body: &Body<'_>, unwind_aware: bool) -> Vec<BasicBlock> {
+    body.basic_blocks
+        .reverse_postorder()
+        .iter()
+        .filter(|bb| unwind_aware || !body.basic_blocks[**bb].is_cleanup)
+        .copied()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rustc_hir::def_id::DefIndex;
+
+    use super::*;
+
+    #[test]
+    fn test_map_layers() {
+        let def_id = |index| DefId::local(DefIndex::from_u32(index));
+        let mut call_graph = CallGraph::new();
+        // 1 -> 2 -> 3, 1 -> 3, 4 -> 3, and 3 calls itself; 5 has no body
+        for (caller, callee) in [(1, 2), (2, 3), (1, 3), (4, 3), (3, 3), (1, 5)] {
+            call_graph.edges.insert((def_id(caller), def_id(callee)));
+        }
+        call_graph.topo = (1..=5).rev().map(def_id).collect();
+        // a body is a successor list of bbs, whose order is its fact
+        let body_of = |def_id: DefId| {
+            let index = def_id.index.as_usize();
+            (index != 5).then(|| (0..index * 50).map(|bb| vec![(bb * 7 + 1) % (index * 50)]).collect())
+        };
+        let order = |body: &Vec<Vec<usize>>| {
+            let mut order = vec![];
+            let mut visited = FxHashSet::default();
+            let mut work_list = vec![0];
+            while let Some(bb) = work_list.pop() {
+                if visited.insert(bb) {
+                    order.push(bb);
+                    work_list.extend(body[bb].iter().copied());
+                }
+            }
+            order
+        };
+
+        let parallel = map_layers(&call_graph.layers(), body_of, order);
+        let sequential: FxHashMap<DefId, Vec<usize>> = call_graph
+            .topo
+            .iter()
+            .filter_map(|def_id| Some((*def_id, order(&body_of(*def_id)?))))
+            .collect();
+        assert_eq!(parallel, sequential);
+        assert_eq!(parallel.len(), 4);
+    }
+}
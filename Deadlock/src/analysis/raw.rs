@@ -0,0 +1,65 @@
+use rustc_hir::def_id::DefId;
+use rustc_middle::mir::Location;
+
+use super::lock::Lock;
+
+type StatementSite = (DefId, Location);
+
+/// an explicit release of a lock, by a raw unlock, `force_unlock`, or a callee
+/// releasing a lock of its caller; a guard dropped after one is recorded too
+#[derive(Debug, Clone)]
+pub struct RawUnlock {
+    pub site: StatementSite,
+    pub lock: Lock,
+    /// whether the lock may be held when it is released
+    pub held: bool,
+    /// the releases of the lock that may come before this one
+    pub released: Vec<StatementSite>,
+}
+
+impl RawUnlock {
+    /// released again, with nothing acquiring the lock in between
+    pub fn is_double(&self) -> bool {
+        !self.held && !self.released.is_empty()
+    }
+
+    /// released without being acquired on the way, which the caller may have done
+    pub fn is_unmatched(&self) -> bool {
+        !self.held && self.released.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustc_hir::def_id::DefIndex;
+    use rustc_middle::mir::START_BLOCK;
+
+    use super::*;
+    use crate::analysis::alias::node::GraphNodeId;
+
+    fn unlock(held: bool, released: usize) -> RawUnlock {
+        let def_id = DefId::local(DefIndex::from_u32(1));
+        let site = |statement_index| (def_id, Location { block: START_BLOCK, statement_index });
+        RawUnlock {
+            site: site(released),
+            lock: Lock::new(GraphNodeId::local(def_id, 1)),
+            held,
+            released: (0..released).map(site).collect(),
+        }
+    }
+
+    #[test]
+    fn test_raw_unlock() {
+        // acquired on the way, maybe after an earlier release
+        for released in 0..2 {
+            assert!(!unlock(true, released).is_double());
+            assert!(!unlock(true, released).is_unmatched());
+        }
+        let twice = unlock(false, 1);
+        assert!(twice.is_double());
+        assert!(!twice.is_unmatched());
+        let unmatched = unlock(false, 0);
+        assert!(!unmatched.is_double());
+        assert!(unmatched.is_unmatched());
+    }
+}
@@ -0,0 +1,228 @@
+//! Function lock summaries shared between crates.
+//!
+//! Every analyzed crate writes the summaries of its functions into
+//! `<target>/deadlock/<crate>.summary`, and crates depending on it load them
+//! lazily when they call one of its functions.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use rustc_hash::FxHashMap;
+use rustc_hir::def_id::{CrateNum, DefId, LOCAL_CRATE};
+use rustc_middle::ty::TyCtxt;
+use serde::{Deserialize, Serialize};
+
+use super::alias::node::EdgeLabel;
+
+/// a lock reachable from a parameter of the function,
+/// e.g. `(*_1).0` is `{ param: 1, projection: [Deref, Field(0)] }`
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockPath {
+    pub param: usize,
+    pub projection: Vec<EdgeLabel>,
+}
+
+impl LockPath {
+    pub fn new(param: usize, projection: Vec<EdgeLabel>) -> Self {
+        Self { param, projection }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FnSummary {
+    /// readable path of the function, only for debugging
+    pub def_path: String,
+    /// locks that may be acquired by the function or its callees
+    pub acquires: Vec<LockPath>,
+    /// (held, acquired) lock pairs observed in the function or its callees
+    pub orders: Vec<(LockPath, LockPath)>,
+    /// locks still held when the function returns, with the path of their guard
+    /// in the return value, e.g. `[]` for a returned `MutexGuard`
+    #[serde(default)]
+    pub returns: Vec<(Vec<EdgeLabel>, LockPath)>,
+    /// locks the function unlocks explicitly without acquiring them, for the caller to hold
+    #[serde(default)]
+    pub releases: Vec<LockPath>,
+    /// locks acquired without a guard and still held when the function returns,
+    /// for the caller to unlock
+    #[serde(default)]
+    pub holds: Vec<LockPath>,
+    /// the function's analysis failed, so what it does is unknown and the rest is empty
+    #[serde(default)]
+    pub unknown: bool,
+    /// a callee's summary, or one of its callees', is unknown, so the rest may miss locks
+    #[serde(default)]
+    pub incomplete: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CrateSummary {
+    pub crate_name: String,
+    /// summaries keyed by the function's def path hash
+    pub functions: FxHashMap<String, FnSummary>,
+}
+
+#[derive(Clone, Default)]
+pub struct SummaryStore {
+    dir: Option<PathBuf>,
+    /// loaded summaries, keyed by the summary file stem;
+    /// `None` if the crate has no summary on disk
+    crates: FxHashMap<String, Option<CrateSummary>>,
+}
+
+impl SummaryStore {
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        Self {
+            dir,
+            crates: FxHashMap::default(),
+        }
+    }
+
+    pub fn get(&mut self, tcx: TyCtxt<'_>, def_id: DefId) -> Option<&FnSummary> {
+        let file = crate_file_stem(tcx, def_id.krate);
+        if !self.crates.contains_key(&file) {
+            // summaries of the current crate are computed in this run, never reuse stale ones
+            let loaded = if def_id.is_local() {
+                None
+            } else {
+                self.load(&file)
+            };
+            self.crates.insert(file.clone(), loaded);
+        }
+        self.crates
+            .get(&file)
+            .unwrap()
+            .as_ref()?
+            .functions
+            .get(&summary_key(tcx, def_id))
+    }
+
+    pub fn insert(&mut self, tcx: TyCtxt<'_>, def_id: DefId, summary: FnSummary) {
+        let crate_summary = self
+            .crates
+            .entry(crate_file_stem(tcx, def_id.krate))
+            .or_insert(None)
+            .get_or_insert_with(|| CrateSummary {
+                crate_name: tcx.crate_name(def_id.krate).to_string(),
+                functions: FxHashMap::default(),
+            });
+        crate_summary
+            .functions
+            .insert(summary_key(tcx, def_id), summary);
+    }
+
+    /// the summaries of the current crate, but the unknown ones, which are no summaries
+    pub fn local_summary(&self, tcx: TyCtxt<'_>) -> CrateSummary {
+        match self.crates.get(&crate_file_stem(tcx, LOCAL_CRATE)) {
+            Some(Some(summary)) => {
+                let mut summary = summary.clone();
+                summary.functions.retain(|_, function| !function.unknown);
+                summary
+            }
+            _ => CrateSummary {
+                crate_name: tcx.crate_name(LOCAL_CRATE).to_string(),
+                functions: FxHashMap::default(),
+            },
+        }
+    }
+
+    /// reuse the summaries of the current crate from a previous run
+    pub fn restore_local(&mut self, tcx: TyCtxt<'_>, summary: CrateSummary) {
+        self.crates
+            .insert(crate_file_stem(tcx, LOCAL_CRATE), Some(summary));
+    }
+
+    /// write the summaries of the current crate into the summary directory
+    pub fn save_local(&self, tcx: TyCtxt<'_>) {
+        let Some(dir) = &self.dir else {
+            return;
+        };
+        let file = crate_file_stem(tcx, LOCAL_CRATE);
+        let summary = self.local_summary(tcx);
+        if let Err(e) = fs::create_dir_all(dir) {
+            tracing::warn!("Cannot create summary directory {:?}: {}", dir, e);
+            return;
+        }
+        let path = dir.join(format!("{}.summary", file));
+        match serde_json::to_string(&summary) {
+            Ok(content) => {
+                if let Err(e) = fs::write(&path, content) {
+                    tracing::warn!("Cannot write summary {:?}: {}", path, e);
+                }
+            }
+            Err(e) => tracing::warn!("Cannot serialize summary of {}: {}", file, e),
+        }
+    }
+
+    fn load(&self, file: &str) -> Option<CrateSummary> {
+        let path = self.dir.as_ref()?.join(format!("{}.summary", file));
+        load_summary(&path)
+    }
+}
+
+fn load_summary(path: &Path) -> Option<CrateSummary> {
+    let content = fs::read_to_string(path).ok()?;
+    match serde_json::from_str(&content) {
+        Ok(summary) => {
+            tracing::debug!("Load lock summary {:?}", path);
+            Some(summary)
+        }
+        Err(e) => {
+            tracing::warn!("Malformed summary {:?}: {}", path, e);
+            None
+        }
+    }
+}
+
+/// crate names are not unique in a dependency graph, so the stable crate id is appended
+pub fn crate_file_stem(tcx: TyCtxt<'_>, krate: CrateNum) -> String {
+    format!(
+        "{}-{:016x}",
+        tcx.crate_name(krate),
+        tcx.stable_crate_id(krate).as_u64()
+    )
+}
+
+/// the def path hash is stable across crates, unlike `DefIndex`
+pub fn summary_key(tcx: TyCtxt<'_>, def_id: DefId) -> String {
+    let (hi, lo) = tcx.def_path_hash(def_id).0.split();
+    format!("{:016x}{:016x}", hi, lo)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_round_trip() {
+        let mut summary = CrateSummary {
+            crate_name: "dep".to_string(),
+            functions: FxHashMap::default(),
+        };
+        let lock = LockPath::new(1, vec![EdgeLabel::Deref, EdgeLabel::Field(0)]);
+        let other = LockPath::new(2, vec![EdgeLabel::Deref]);
+        summary.functions.insert(
+            "0123".to_string(),
+            FnSummary {
+                def_path: "dep::get_lock".to_string(),
+                acquires: vec![lock.clone(), other.clone()],
+                orders: vec![(lock.clone(), other.clone())],
+                returns: vec![(vec![EdgeLabel::Field(1)], other.clone())],
+                releases: vec![lock.clone()],
+                holds: vec![],
+                unknown: false,
+                incomplete: false,
+            },
+        );
+
+        let content = serde_json::to_string(&summary).unwrap();
+        let loaded: CrateSummary = serde_json::from_str(&content).unwrap();
+        let f = &loaded.functions["0123"];
+        assert_eq!(f.acquires, vec![lock.clone(), other.clone()]);
+        assert_eq!(f.orders, vec![(lock.clone(), other.clone())]);
+        assert_eq!(f.returns, vec![(vec![EdgeLabel::Field(1)], other)]);
+        assert_eq!(f.releases, vec![lock]);
+    }
+}
@@ -0,0 +1,18 @@
+use rustc_hir::def_id::DefId;
+use rustc_middle::mir::Location;
+
+use super::{alias::node::GraphNodeId, lock::Lock};
+
+type StatementSite = (DefId, Location);
+
+/// a thread spawned with a closure
+#[derive(Debug, Clone)]
+pub struct ThreadSpawn {
+    pub site: StatementSite,
+    pub closure: DefId,
+    /// the node of the returned handle, to find where the thread is joined
+    pub handle: GraphNodeId,
+    /// locks the thread may acquire in the spawner's terms,
+    /// with the acquisition site if it is in the closure itself
+    pub acquires: Vec<(Lock, Option<StatementSite>)>,
+}
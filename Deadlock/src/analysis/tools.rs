@@ -1,11 +1,16 @@
 use rustc_hash::FxHashSet;
 use rustc_hir::def_id::DefId;
 use rustc_middle::{
-    mir::{self, Local, Place},
-    ty::{self, Ty},
+    mir::{
+        self,
+        interpret::{GlobalAlloc, Scalar},
+        Local, Place,
+    },
+    ty::{self, Ty, TyCtxt},
 };
+use rustc_span::sym;
 
-use super::LockSetAnalysis;
+use super::{condvar::CondvarOpKind, LockSetAnalysis};
 
 /// whether a type is lock
 pub fn is_lock(ty: &Ty) -> bool {
@@ -43,11 +48,278 @@ pub fn is_mutex_method(def_path: &String) -> bool {
 }
 
 pub fn is_smart_pointer(def_path: &String) -> bool {
-    def_path.starts_with("std::sync::Arc")
+    def_path.starts_with("std::sync::Arc") || def_path.starts_with("std::rc::Rc")
 }
 
+/// `RefCell::borrow` and `borrow_mut`, with whether the borrow is mutable
+pub fn refcell_borrow(def_path: &String) -> Option<bool> {
+    match normalize_path(def_path).as_str() {
+        "std::cell::RefCell::borrow" => Some(false),
+        "std::cell::RefCell::borrow_mut" => Some(true),
+        _ => None,
+    }
+}
+
+/// acquisitions of parking_lot's mutex through `lock_api`,
+/// with whether the mutex is reached through an `Arc`, as `lock_arc` takes `&Arc<Self>`
+pub fn lock_api_acquire(def_path: &String) -> Option<bool> {
+    match normalize_path(def_path).as_str() {
+        "lock_api::Mutex::lock" => Some(false),
+        "lock_api::Mutex::lock_arc" => Some(true),
+        _ => None,
+    }
+}
+
+/// guard-less operations of `lock_api`, which FFI glue uses to pair a lock and an unlock
+/// by hand, maybe in different functions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RawLockOp {
+    /// `RawMutex::lock(&self)`, held until an explicit unlock
+    Lock,
+    /// `RawMutex::unlock(&self)` or `Mutex::force_unlock(&self)`, whoever holds the lock
+    Unlock,
+    /// `MutexGuard::leak(guard)`, the lock stays held without its guard
+    Leak,
+    /// `Mutex::raw(&self)`, the raw mutex is the same lock as the mutex
+    Raw,
+}
+
+pub fn raw_lock_op(def_path: &String) -> Option<RawLockOp> {
+    match normalize_path(def_path).as_str() {
+        "lock_api::RawMutex::lock" => Some(RawLockOp::Lock),
+        "lock_api::RawMutex::unlock"
+        | "lock_api::RawMutexFair::unlock_fair"
+        | "lock_api::Mutex::force_unlock"
+        | "lock_api::Mutex::force_unlock_fair" => Some(RawLockOp::Unlock),
+        "lock_api::MutexGuard::leak" => Some(RawLockOp::Leak),
+        "lock_api::Mutex::raw" => Some(RawLockOp::Raw),
+        _ => None,
+    }
+}
+
+/// the guards of std and `lock_api`, mapped or not
+const GUARDS: [&str; 12] = [
+    "std::sync::MutexGuard",
+    "std::sync::MappedMutexGuard",
+    "std::sync::RwLockReadGuard",
+    "std::sync::RwLockWriteGuard",
+    "std::sync::MappedRwLockReadGuard",
+    "std::sync::MappedRwLockWriteGuard",
+    "lock_api::MutexGuard",
+    "lock_api::MappedMutexGuard",
+    "lock_api::RwLockReadGuard",
+    "lock_api::RwLockWriteGuard",
+    "lock_api::MappedRwLockReadGuard",
+    "lock_api::MappedRwLockWriteGuard",
+];
+
+/// guard projections, which keep the original lock held,
+/// with whether the projected guard is returned in a `Result`
+pub fn guard_map(def_path: &String) -> Option<bool> {
+    let path = normalize_path(def_path);
+    let (guard, method) = path.rsplit_once("::")?;
+    if !GUARDS.contains(&guard) {
+        return None;
+    }
+    match method {
+        "map" => Some(false),
+        "try_map" | "filter_map" => Some(true),
+        _ => None,
+    }
+}
+
+/// acquisitions that give up instead of blocking forever,
+/// returning the guard in an `Ok` or a `Some` on success
+pub fn is_try_lock_method(def_path: &String) -> bool {
+    matches!(
+        normalize_path(def_path).as_str(),
+        "std::sync::Mutex::try_lock"
+            | "std::sync::RwLock::try_read"
+            | "std::sync::RwLock::try_write"
+            | "lock_api::Mutex::try_lock"
+            | "lock_api::Mutex::try_lock_for"
+            | "lock_api::Mutex::try_lock_until"
+            | "lock_api::RwLock::try_read"
+            | "lock_api::RwLock::try_read_for"
+            | "lock_api::RwLock::try_read_until"
+            | "lock_api::RwLock::try_write"
+            | "lock_api::RwLock::try_write_for"
+            | "lock_api::RwLock::try_write_until"
+            | "tokio::sync::Mutex::try_lock"
+            | "tokio::sync::RwLock::try_read"
+            | "tokio::sync::RwLock::try_write"
+    )
+}
+
+/// the discriminant of the `Ok` or `Some` a successful try-acquire returns
+pub fn success_discriminant<'tcx>(tcx: TyCtxt<'tcx>, ty: Ty<'tcx>) -> Option<u128> {
+    let ty::Adt(adt, _) = ty.kind() else {
+        return None;
+    };
+    if tcx.is_diagnostic_item(sym::Result, adt.did()) {
+        Some(0)
+    } else if tcx.is_diagnostic_item(sym::Option, adt.did()) {
+        Some(1)
+    } else {
+        None
+    }
+}
+
+/// methods returning a future that resolves to a guard
+pub fn is_async_lock_method(def_path: &String) -> bool {
+    matches!(
+        normalize_path(def_path).as_str(),
+        "tokio::sync::Mutex::lock"
+            | "tokio::sync::Mutex::lock_owned"
+            | "tokio::sync::RwLock::read"
+            | "tokio::sync::RwLock::write"
+            | "tokio::sync::RwLock::read_owned"
+            | "tokio::sync::RwLock::write_owned"
+            | "futures::lock::Mutex::lock"
+            | "futures_util::lock::Mutex::lock"
+    )
+}
+
+pub fn is_future_poll(def_path: &String) -> bool {
+    def_path.ends_with("::poll") && def_path.contains("Future")
+}
+
+/// calls returning the future they take, as `.await` wraps it before polling
+pub fn is_future_wrapper(def_path: &String) -> bool {
+    def_path.ends_with("::into_future")
+        || normalize_path(def_path).starts_with("std::pin::Pin::new")
+}
+
+/// whether a type is a guard holding a lock, mapped or not
 pub fn is_guard(ty: &Ty) -> bool {
-    format!("{:?}", ty).starts_with("std::sync::MutexGuard")
+    match ty.kind() {
+        ty::Adt(..) => GUARDS.contains(&normalize_path(&format!("{:?}", ty)).as_str()),
+        _ => false,
+    }
+}
+
+/// drop generic arguments and qualified self types,
+/// e.g. `<std::fs::File as std::io::Read>::read` becomes `std::fs::File::read`
+pub fn normalize_path(path: &str) -> String {
+    let path = match path.strip_prefix('<').and_then(|rest| rest.split_once(" as ")) {
+        Some((self_ty, rest)) => match rest.split_once(">::") {
+            Some((_, item)) => format!("{}::{}", self_ty, item),
+            None => path.to_string(),
+        },
+        None => path.to_string(),
+    };
+    let mut normalized = String::new();
+    let mut depth = 0;
+    for c in path.chars() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            _ if depth == 0 => normalized.push(c),
+            _ => {}
+        }
+    }
+    let normalized = normalized.replace("::::", "::");
+    // parking_lot re-exports lock_api, whose items print through it unless lock_api
+    // is a dependency of its own
+    match normalized.strip_prefix("parking_lot::") {
+        Some(rest) if rest.starts_with("lock_api::") => rest.to_string(),
+        _ => normalized,
+    }
+}
+
+pub fn is_mem_drop(def_path: &String) -> bool {
+    matches!(normalize_path(def_path).as_str(), "std::mem::drop" | "core::mem::drop")
+}
+
+pub fn is_thread_spawn(def_path: &String) -> bool {
+    matches!(
+        normalize_path(def_path).as_str(),
+        "std::thread::spawn"
+            | "std::thread::Builder::spawn"
+            | "std::thread::Scope::spawn"
+            | "tokio::spawn"
+            | "tokio::task::spawn"
+            | "tokio::task::spawn_blocking"
+            | "tokio::task::spawn_local"
+            | "tokio::runtime::Runtime::spawn"
+            | "tokio::runtime::Handle::spawn"
+    )
+}
+
+pub fn is_thread_join(def_path: &String) -> bool {
+    matches!(
+        normalize_path(def_path).as_str(),
+        "std::thread::JoinHandle::join" | "std::thread::ScopedJoinHandle::join"
+    )
+}
+
+pub enum ChannelOp {
+    /// returns a (sender, receiver) pair
+    Create { bounded: bool },
+    /// `None` if only a bounded channel blocks the sender
+    Send { blocking: Option<bool> },
+    Recv,
+}
+
+pub fn channel_op(def_path: &String) -> Option<ChannelOp> {
+    let def_path = normalize_path(def_path);
+    match def_path.as_str() {
+        "std::sync::mpsc::channel" | "crossbeam_channel::unbounded" => {
+            Some(ChannelOp::Create { bounded: false })
+        }
+        "std::sync::mpsc::sync_channel" | "crossbeam_channel::bounded" => {
+            Some(ChannelOp::Create { bounded: true })
+        }
+        "std::sync::mpsc::Sender::send" => Some(ChannelOp::Send {
+            blocking: Some(false),
+        }),
+        "std::sync::mpsc::SyncSender::send" => Some(ChannelOp::Send {
+            blocking: Some(true),
+        }),
+        "crossbeam_channel::Sender::send" => Some(ChannelOp::Send { blocking: None }),
+        "std::sync::mpsc::Receiver::recv"
+        | "std::sync::mpsc::Receiver::recv_timeout"
+        | "crossbeam_channel::Receiver::recv"
+        | "crossbeam_channel::Receiver::recv_timeout" => Some(ChannelOp::Recv),
+        _ => None,
+    }
+}
+
+pub fn condvar_op(def_path: &String) -> Option<CondvarOpKind> {
+    let def_path = normalize_path(def_path);
+    let name = def_path.strip_prefix("std::sync::Condvar::")?;
+    match name {
+        "wait" => Some(CondvarOpKind::Wait),
+        "wait_while" | "wait_timeout_while" => Some(CondvarOpKind::WaitWhile),
+        "wait_timeout" | "wait_timeout_ms" => Some(CondvarOpKind::WaitTimeout),
+        "notify_one" => Some(CondvarOpKind::NotifyOne),
+        "notify_all" => Some(CondvarOpKind::NotifyAll),
+        _ => None,
+    }
+}
+
+/// the callee of a direct call, `None` for calls through fn pointers or closures
+pub fn callee_def_id(func: &mir::Operand) -> Option<DefId> {
+    if let mir::Operand::Constant(constant) = func {
+        if let ty::FnDef(def_id, _) = constant.ty().kind() {
+            return Some(*def_id);
+        }
+    }
+    None
+}
+
+/// the static a `const &STATIC` operand refers to
+pub fn static_ref(tcx: TyCtxt<'_>, op: &mir::Operand) -> Option<DefId> {
+    let mir::Operand::Constant(constant) = op else {
+        return None;
+    };
+    let Some(Scalar::Ptr(ptr, _)) = constant.const_.try_to_scalar() else {
+        return None;
+    };
+    match tcx.try_get_global_alloc(ptr.provenance.alloc_id())? {
+        GlobalAlloc::Static(def_id) => Some(def_id),
+        _ => None,
+    }
 }
 
 impl<'a, 'tcx> LockSetAnalysis<'a, 'tcx> {
@@ -55,3 +327,22 @@ impl<'a, 'tcx> LockSetAnalysis<'a, 'tcx> {
         self.my_tcx.tcx.optimized_mir(def_id).local_decls[Local::from_usize(index)].ty
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guard_maps() {
+        let path = |path: &str| path.to_string();
+        assert_eq!(guard_map(&path("std::sync::MutexGuard::<'_, Pair>::map")), Some(false));
+        assert_eq!(guard_map(&path("lock_api::MutexGuard::try_map")), Some(true));
+        assert_eq!(guard_map(&path("lock_api::MappedMutexGuard::map")), Some(false));
+        assert_eq!(guard_map(&path("std::option::Option::map")), None);
+        assert_eq!(lock_api_acquire(&path("lock_api::Mutex::<R, T>::lock_arc")), Some(true));
+        assert_eq!(
+            lock_api_acquire(&path("parking_lot::lock_api::Mutex::<R, T>::lock")),
+            Some(false)
+        );
+    }
+}
@@ -22,21 +22,26 @@ fn main() -> std::io::Result<()> {
     // 复制文件到当前 crate 的根目录
     fs::copy(&cargo_toolchain_path, &target_path)?;
 
-    tracing::trace!("Start to cargo clean.");
-    // 执行 `cargo clean`
-    let clean_status = Command::new("cargo")
-        .arg("clean")
-        .status()
-        .expect("Failed to execute cargo clean");
-
-    if !clean_status.success() {
-        eprintln!("cargo clean failed");
-        return Ok(());
+    // 获取命令行参数并传递给 `cargo deadlock`
+    let mut args: Vec<String> = env::args().skip(1).collect();
+
+    // analysis results are cached per crate, so only clean when asked to
+    if args.iter().any(|arg| arg == "--clean") {
+        args.retain(|arg| arg != "--clean");
+        tracing::trace!("Start to cargo clean.");
+        // 执行 `cargo clean`
+        let clean_status = Command::new("cargo")
+            .arg("clean")
+            .status()
+            .expect("Failed to execute cargo clean");
+
+        if !clean_status.success() {
+            eprintln!("cargo clean failed");
+            return Ok(());
+        }
+        tracing::trace!("Finish to cargo clean.");
     }
-    tracing::trace!("Finish to cargo clean.");
 
-    // 获取命令行参数并传递给 `cargo deadlock`
-    let args: Vec<String> = env::args().skip(1).collect();
     let mut binding = Command::new("cargo");
     let cmd = binding.arg("deadlock").args(&args);
     tracing::info!("Start to exec: {:?}", cmd);
@@ -7,5 +7,5 @@ fn main() {
     dotenvy::dotenv().ok();
     tracing_subscriber::fmt::init();
     tracing::trace!("cargo deadlock driver start to run!");
-    cargo_main(MyDriver);
+    cargo_main(MyDriver::default());
 }
@@ -6,5 +6,5 @@ use rustc_compat::rustc_main;
 fn main() {
     tracing_subscriber::fmt::init();
     tracing::trace!("run deadlock detection");
-    rustc_main(MyDriver);
+    rustc_main(MyDriver::default());
 }
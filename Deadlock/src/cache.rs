@@ -0,0 +1,352 @@
+//! Per-crate analysis cache.
+//!
+//! A crate's results are keyed by its fingerprint: the hashes of its source
+//! files, the analysis options, the passes run and the summaries of its
+//! dependencies. When cargo re-runs the driver on a crate whose fingerprint is
+//! unchanged, the cached summary and findings are reused instead of analyzing it
+//! again.
+//!
+//! The cache outlives the crates it holds results of, so the cargo frontend only
+//! reports the crates of its own invocation: the drivers it runs record their crate
+//! in a directory of the run, the workspace crates cargo found fresh are the ones
+//! of the previous invocation, and the rest are their dependencies.
+
+use std::{
+    env, fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+use rustc_hash::{FxHashMap, FxHashSet, FxHasher};
+use rustc_hir::def_id::LOCAL_CRATE;
+use rustc_middle::ty::TyCtxt;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    analysis::summary::{crate_file_stem, CrateSummary},
+    option::Options,
+    report::{Finding, Reporter},
+};
+
+/// set by the cargo frontend for the drivers it runs, naming its invocation
+pub const RUN_ENV: &str = "DEADLOCK_RUN";
+/// the crates the previous invocation reported
+const LAST_RUN: &str = "last-run.manifest";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub krate: String,
+    pub fingerprint: String,
+    pub summary: CrateSummary,
+    pub findings: Vec<Finding>,
+    #[serde(default)]
+    pub notes: Vec<String>,
+    /// cargo checks the crate as a workspace member rather than as a dependency
+    #[serde(default)]
+    pub primary: bool,
+    /// the file stems of the crates it depends on
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+}
+
+impl CacheEntry {
+    pub fn new(tcx: TyCtxt<'_>, fingerprint: String) -> Self {
+        Self {
+            krate: tcx.crate_name(LOCAL_CRATE).to_string(),
+            fingerprint,
+            summary: CrateSummary::default(),
+            findings: vec![],
+            notes: vec![],
+            primary: env::var_os("CARGO_PRIMARY_PACKAGE").is_some(),
+            dependencies: tcx
+                .crates(())
+                .iter()
+                .map(|krate| crate_file_stem(tcx, *krate))
+                .collect(),
+        }
+    }
+}
+
+/// `passes` are the names of the passes the strategy runs, custom checkers included
+pub fn fingerprint(tcx: TyCtxt<'_>, options: &Options, passes: &[String]) -> String {
+    let sources: Vec<_> = tcx
+        .sess
+        .source_map()
+        .files()
+        .iter()
+        .map(|file| (file.name.clone(), file.src_hash))
+        .collect();
+    // a dependency's summary changes the results of its dependents
+    let mut summaries = vec![];
+    if let Some(dir) = options.summary_dir() {
+        for krate in tcx.crates(()) {
+            let path = dir.join(format!("{}.summary", crate_file_stem(tcx, *krate)));
+            if let Ok(content) = fs::read(path) {
+                summaries.push(content);
+            }
+        }
+    }
+    hash_inputs(options, passes, &sources, &summaries)
+}
+
+/// the fingerprint of the options, the passes, the sources and the summaries of the
+/// dependencies a crate is analyzed with
+fn hash_inputs(
+    options: &Options,
+    passes: &[String],
+    sources: &impl Hash,
+    summaries: &[Vec<u8>],
+) -> String {
+    let mut hasher = FxHasher::default();
+    env!("CARGO_PKG_VERSION").hash(&mut hasher);
+    serde_json::to_string(options)
+        .unwrap_or_default()
+        .hash(&mut hasher);
+    passes.hash(&mut hasher);
+    sources.hash(&mut hasher);
+    summaries.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// the cached results of the current crate, if they are up to date
+pub fn load(dir: &Path, tcx: TyCtxt<'_>, fingerprint: &str) -> Option<CacheEntry> {
+    load_fresh(&entry_path(dir, tcx), fingerprint)
+}
+
+fn load_fresh(path: &Path, fingerprint: &str) -> Option<CacheEntry> {
+    let entry = load_entry(path)?;
+    if entry.fingerprint == fingerprint {
+        tracing::info!("Reuse cached results of crate {}", entry.krate);
+        Some(entry)
+    } else {
+        None
+    }
+}
+
+pub fn store(dir: &Path, tcx: TyCtxt<'_>, entry: &CacheEntry) {
+    if let Err(e) = fs::create_dir_all(dir) {
+        tracing::warn!("Cannot create cache directory {:?}: {}", dir, e);
+        return;
+    }
+    store_entry(&entry_path(dir, tcx), entry);
+}
+
+fn entry_path(dir: &Path, tcx: TyCtxt<'_>) -> PathBuf {
+    dir.join(format!("{}.json", crate_file_stem(tcx, LOCAL_CRATE)))
+}
+
+fn store_entry(path: &Path, entry: &CacheEntry) {
+    match serde_json::to_string(entry) {
+        Ok(content) => {
+            if let Err(e) = fs::write(path, content) {
+                tracing::warn!("Cannot write cache {:?}: {}", path, e);
+            }
+        }
+        Err(e) => tracing::warn!("Cannot serialize cache of {}: {}", entry.krate, e),
+    }
+}
+
+/// record that a driver of the current invocation ran on the crate
+pub fn touch(dir: &Path, tcx: TyCtxt<'_>) {
+    let Ok(run) = env::var(RUN_ENV) else {
+        return;
+    };
+    let run_dir = dir.join(format!("run-{}", run));
+    let path = run_dir.join(crate_file_stem(tcx, LOCAL_CRATE));
+    if let Err(e) = fs::create_dir_all(&run_dir).and_then(|_| fs::write(&path, "")) {
+        tracing::warn!("Cannot record crate in {:?}: {}", run_dir, e);
+    }
+}
+
+/// the cached crates of an invocation of the cargo frontend, once cargo is done:
+/// the workspace crates its drivers ran on, the ones cargo found fresh, which are
+/// the previous invocation's, and their dependencies
+pub fn load_run(dir: &Path, run: &str) -> Vec<CacheEntry> {
+    let run_dir = dir.join(format!("run-{}", run));
+    let touched: FxHashSet<String> = fs::read_dir(&run_dir)
+        .map(|dir_entries| {
+            dir_entries
+                .flatten()
+                .map(|dir_entry| dir_entry.file_name().to_string_lossy().into_owned())
+                .collect()
+        })
+        .unwrap_or_default();
+    let _ = fs::remove_dir_all(&run_dir);
+    let previous: FxHashSet<String> = fs::read_to_string(dir.join(LAST_RUN))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+
+    let mut entries = FxHashMap::default();
+    if let Ok(dir_entries) = fs::read_dir(dir) {
+        for dir_entry in dir_entries.flatten() {
+            let path = dir_entry.path();
+            if path.extension().map_or(false, |ext| ext == "json") {
+                if let (Some(stem), Some(entry)) = (path.file_stem(), load_entry(&path)) {
+                    entries.insert(stem.to_string_lossy().into_owned(), entry);
+                }
+            }
+        }
+    }
+    // a crate rebuilt under another stem leaves the previous one stale
+    let rebuilt: FxHashSet<&str> = touched
+        .iter()
+        .filter_map(|stem| entries.get(stem))
+        .map(|entry: &CacheEntry| entry.krate.as_str())
+        .collect();
+    let mut work_list: Vec<String> = entries
+        .iter()
+        .filter(|(stem, entry)| {
+            entry.primary
+                && (touched.contains(*stem)
+                    || previous.contains(*stem) && !rebuilt.contains(entry.krate.as_str()))
+        })
+        .map(|(stem, _)| stem.clone())
+        .collect();
+    let mut current = FxHashSet::default();
+    while let Some(stem) = work_list.pop() {
+        if let Some(entry) = entries.get(&stem) {
+            if current.insert(stem) {
+                work_list.extend(entry.dependencies.iter().cloned());
+            }
+        }
+    }
+    let mut manifest: Vec<&String> = current.iter().collect();
+    manifest.sort();
+    if let Err(e) = fs::write(
+        dir.join(LAST_RUN),
+        serde_json::to_string(&manifest).unwrap_or_default(),
+    ) {
+        tracing::warn!("Cannot write the crates of the run into {:?}: {}", dir, e);
+    }
+
+    let mut entries: Vec<CacheEntry> = entries
+        .into_iter()
+        .filter(|(stem, _)| current.contains(stem))
+        .map(|(_, entry)| entry)
+        .collect();
+    entries.sort_by(|a, b| a.krate.cmp(&b.krate));
+    entries
+}
+
+/// the reports `after_cargo` prints for an invocation, by crate; a dependency is
+/// only worth a line when it has a bug
+pub fn run_reports(dir: &Path, run: &str) -> Vec<(String, Reporter)> {
+    load_run(dir, run)
+        .into_iter()
+        .filter(|entry| entry.primary || !entry.findings.is_empty())
+        .map(|entry| (entry.krate, Reporter::from(entry.findings).with_notes(entry.notes)))
+        .collect()
+}
+
+fn load_entry(path: &Path) -> Option<CacheEntry> {
+    let content = fs::read_to_string(path).ok()?;
+    match serde_json::from_str(&content) {
+        Ok(entry) => Some(entry),
+        Err(e) => {
+            tracing::warn!("Malformed cache {:?}: {}", path, e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    fn options(args: &[&str]) -> Options {
+        Options::parse_from(["deadlock"].iter().chain(args))
+    }
+
+    fn entry(krate: &str, fingerprint: &str, findings: Vec<Finding>) -> CacheEntry {
+        CacheEntry {
+            krate: krate.to_string(),
+            fingerprint: fingerprint.to_string(),
+            summary: CrateSummary::default(),
+            findings,
+            notes: vec![],
+            primary: false,
+            dependencies: vec![],
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("deadlock-cache-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_hit() {
+        let dir = temp_dir("hit");
+        let path = dir.join("app-1.json");
+        let finding = Finding::new("double-lock", String::from("possible double lock"));
+        store_entry(&path, &entry("app", "0123", vec![finding.clone()]));
+
+        let cached = load_fresh(&path, "0123").unwrap();
+        assert_eq!(cached.krate, "app");
+        assert_eq!(cached.findings, vec![finding]);
+        // another fingerprint is a miss
+        assert!(load_fresh(&path, "4567").is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_invalidation() {
+        let passes = vec![String::from("LockSet")];
+        let sources = vec![("src/main.rs", 1u64)];
+        let summaries = vec![b"lock_a".to_vec()];
+        let base = hash_inputs(&options(&[]), &passes, &sources, &summaries);
+        assert_eq!(base, hash_inputs(&options(&[]), &passes, &sources, &summaries));
+
+        // a source edited
+        let edited = vec![("src/main.rs", 2u64)];
+        assert_ne!(base, hash_inputs(&options(&[]), &passes, &edited, &summaries));
+        // another option
+        let inclusion = options(&["--alias-mode", "inclusion"]);
+        assert_ne!(base, hash_inputs(&inclusion, &passes, &sources, &summaries));
+        // another pass, e.g. a custom checker
+        let more = vec![String::from("LockSet"), String::from("Custom")];
+        assert_ne!(base, hash_inputs(&options(&[]), &more, &sources, &summaries));
+        // a dependency's summary changed
+        let changed = vec![b"lock_a again".to_vec()];
+        assert_ne!(base, hash_inputs(&options(&[]), &passes, &sources, &changed));
+    }
+
+    #[test]
+    fn test_run_reports() {
+        let dir = temp_dir("run");
+        let finding = Finding::new("lock-order", String::from("possible deadlock"))
+            .with_trace(String::from("lock `_1` in main"));
+        // the workspace crate the driver ran on, and its dependencies with and
+        // without a bug, all cached
+        let app = CacheEntry {
+            primary: true,
+            dependencies: vec![String::from("dep-1"), String::from("clean-1")],
+            notes: vec![String::from("2 lock node(s)")],
+            ..entry("app", "1", vec![finding.clone()])
+        };
+        store_entry(&dir.join("app-1.json"), &app);
+        store_entry(&dir.join("dep-1.json"), &entry("dep", "2", vec![finding]));
+        store_entry(&dir.join("clean-1.json"), &entry("clean", "3", vec![]));
+        store_entry(&dir.join("other-1.json"), &entry("other", "4", vec![]));
+        fs::create_dir_all(dir.join("run-7")).unwrap();
+        fs::write(dir.join("run-7").join("app-1"), "").unwrap();
+
+        let reports = run_reports(&dir, "7");
+        let krates: Vec<&str> = reports.iter().map(|(krate, _)| krate.as_str()).collect();
+        assert_eq!(krates, vec!["app", "dep"]);
+        let printed = reports[0].1.render(&reports[0].0);
+        assert!(printed.starts_with("1 bug(s) found in crate app:"));
+        assert!(printed.contains("[lock-order] possible deadlock\n    lock `_1` in main\n"));
+        assert!(printed.ends_with("note: 2 lock node(s)\n"));
+
+        // cargo finds the crate fresh the next time, so no driver runs on it
+        let reports = run_reports(&dir, "8");
+        assert_eq!(reports.len(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
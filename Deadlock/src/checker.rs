@@ -0,0 +1,262 @@
+//! Bug checkers.
+//!
+//! A checker runs after the lock set analysis and gets read access to its
+//! results. Besides the built-in ones, downstream crates can implement
+//! `BugChecker` and register it in a custom driver:
+//!
+//! ```ignore
+//! fn main() {
+//!     let driver = MyDriver::default()
+//!         .with_driver_name("my-deadlock")
+//!         .with_checker(NoRpcUnderDbLock);
+//!     rustc_compat::rustc_main(driver);
+//! }
+//! ```
+//!
+//! The driver name is the name of the binary Cargo runs in place of rustc,
+//! `deadlock` unless it is given.
+
+use rustc_hir::def_id::DefId;
+use rustc_middle::{
+    mir::{BasicBlock, Body, Location, Place, TerminatorKind},
+    ty::TyCtxt,
+};
+use rustc_span::Span;
+
+use crate::{
+    analysis::{
+        alias::node::GraphNodeId,
+        borrow::BorrowOp,
+        callgraph::CallGraph,
+        condvar::CondvarOp,
+        isolate::isolate,
+        lock::{Lock, LockSummary},
+        lockgraph::LockGraph,
+        locks_of,
+        raw::RawUnlock,
+        thread::ThreadSpawn,
+        tools::callee_def_id,
+    },
+    context::MyTcx,
+    option::Options,
+    pass::{AnalysisPass, Artifact},
+    report::Reporter,
+};
+
+pub mod await_lock;
+pub mod blocking;
+pub mod condvar;
+pub mod join;
+pub mod leak;
+pub mod lock_order;
+pub mod poison;
+pub mod refcell;
+pub mod unlock;
+
+pub trait BugChecker: Send {
+    fn name(&self) -> String;
+
+    fn check<'tcx>(&mut self, cx: &CheckerContext<'_, 'tcx>, reporter: &mut Reporter);
+}
+
+/// read-only view of the analysis results
+pub struct CheckerContext<'a, 'tcx> {
+    my_tcx: &'a MyTcx<'tcx>,
+}
+
+impl<'a, 'tcx> CheckerContext<'a, 'tcx> {
+    pub(crate) fn new(my_tcx: &'a MyTcx<'tcx>) -> Self {
+        Self { my_tcx }
+    }
+
+    pub fn tcx(&self) -> TyCtxt<'tcx> {
+        self.my_tcx.tcx
+    }
+
+    pub fn options(&self) -> &Options {
+        &self.my_tcx.options
+    }
+
+    pub fn call_graph(&self) -> &CallGraph<'tcx> {
+        &self.my_tcx.call_graph
+    }
+
+    pub fn lock_graph(&self) -> &LockGraph {
+        &self.my_tcx.lock_graph
+    }
+
+    /// functions with lock set facts, i.e. the analyzed ones
+    pub fn functions(&self) -> Vec<DefId> {
+        let mut functions: Vec<DefId> = self.my_tcx.lock_set_facts.keys().copied().collect();
+        functions.sort_by_key(|def_id| def_id.index);
+        functions
+    }
+
+    pub fn is_analyzed(&self, def_id: DefId) -> bool {
+        self.my_tcx.lock_set_facts.contains_key(&def_id)
+    }
+
+    pub fn body(&self, def_id: DefId) -> &'tcx Body<'tcx> {
+        self.my_tcx.tcx.optimized_mir(def_id)
+    }
+
+    /// lock facts at the terminator of a bb, `None` if the bb is not analyzed
+    pub fn lock_facts(&self, def_id: DefId, bb: BasicBlock) -> Option<&LockSummary> {
+        self.my_tcx
+            .lock_set_facts
+            .get(&def_id)?
+            .get(&bb.as_usize())
+    }
+
+    /// locks held at a location; statements never change the lock set,
+    /// so this is the lock set of the location's bb
+    pub fn held_locks(&self, def_id: DefId, location: Location) -> Vec<Lock> {
+        self.my_tcx
+            .held_locks(&def_id, location.block.as_usize())
+    }
+
+    /// the locks a place may name, empty if the alias analysis never saw it
+    pub fn locks_of_place(&self, def_id: DefId, place: &Place<'tcx>) -> Vec<Lock> {
+        match self.my_tcx.alias_graph.find_place(&def_id, place) {
+            Some(node) => locks_of(&self.my_tcx.alias_graph, node),
+            None => vec![],
+        }
+    }
+
+    /// whether two places of a function may name the same location, false if the alias
+    /// analysis never saw one of them
+    pub fn may_alias(&self, def_id: DefId, a: &Place<'tcx>, b: &Place<'tcx>) -> bool {
+        let alias_graph = &self.my_tcx.alias_graph;
+        match (alias_graph.find_place(&def_id, a), alias_graph.find_place(&def_id, b)) {
+            (Some(a), Some(b)) => alias_graph.may_alias(a, b),
+            _ => false,
+        }
+    }
+
+    /// where a held lock was acquired, if it is held at the location
+    pub fn acquisition_site(
+        &self,
+        def_id: DefId,
+        location: Location,
+        lock: &Lock,
+    ) -> Option<(DefId, Location)> {
+        self.lock_facts(def_id, location.block)?
+            .iter()
+            .flatten()
+            .find(|fact| fact.is_acquisition && !fact.state && fact.lock == *lock)
+            .map(|fact| fact.s_location)
+    }
+
+    /// every site acquiring a lock in a function
+    pub fn acquisitions(&self, def_id: DefId, lock: &Lock) -> Vec<(DefId, Location)> {
+        let mut sites = vec![];
+        let Some(facts) = self.my_tcx.lock_set_facts.get(&def_id) else {
+            return sites;
+        };
+        for fact in facts.values().flatten().flatten() {
+            if fact.is_acquisition && fact.lock == *lock && !sites.contains(&fact.s_location) {
+                sites.push(fact.s_location);
+            }
+        }
+        sites
+    }
+
+    /// the function called at a location, if it is a call terminator
+    pub fn callee_at(&self, def_id: DefId, location: Location) -> Option<DefId> {
+        let data = &self.body(def_id).basic_blocks[location.block];
+        if location.statement_index != data.statements.len() {
+            return None;
+        }
+        match &data.terminator().kind {
+            TerminatorKind::Call { func, .. } => callee_def_id(func),
+            _ => None,
+        }
+    }
+
+    pub fn threads(&self) -> &[ThreadSpawn] {
+        &self.my_tcx.threads
+    }
+
+    /// condvar operations, and their copies in the callers and spawners
+    pub fn condvar_ops(&self) -> &[CondvarOp] {
+        &self.my_tcx.condvar_ops
+    }
+
+    /// `RefCell` borrows, and their copies in the callers
+    pub fn borrow_ops(&self) -> &[BorrowOp] {
+        &self.my_tcx.borrow_ops
+    }
+
+    /// explicit releases of locks, and the calls releasing a lock of the caller
+    pub fn raw_unlocks(&self) -> &[RawUnlock] {
+        &self.my_tcx.raw_unlocks
+    }
+
+    /// threads whose handle may be the local joined at a place
+    pub fn joined_threads(&self, def_id: DefId, handle: &Place<'tcx>) -> Vec<&ThreadSpawn> {
+        let alias_graph = &self.my_tcx.alias_graph;
+        let id = GraphNodeId::local(def_id, handle.local.as_usize());
+        let Some(node) = alias_graph.get_node(&id) else {
+            return vec![];
+        };
+        self.my_tcx
+            .threads
+            .iter()
+            .filter(|thread| alias_graph.get_node(&thread.handle) == Some(node))
+            .collect()
+    }
+
+    pub fn span(&self, def_id: DefId, location: Location) -> Span {
+        self.body(def_id).source_info(location).span
+    }
+
+    /// a readable form of a location, to be put in a finding's trace
+    pub fn describe_location(&self, def_id: DefId, location: Location) -> String {
+        self.describe_span(self.span(def_id, location))
+    }
+
+    pub fn describe_span(&self, span: Span) -> String {
+        self.my_tcx
+            .tcx
+            .sess
+            .source_map()
+            .span_to_diagnostic_string(span)
+    }
+
+    pub fn describe_lock(&self, lock: &Lock) -> String {
+        lock.describe(self.my_tcx.tcx)
+    }
+}
+
+/// run a checker as a pass of a strategy
+pub struct CheckerPass {
+    checker: Box<dyn BugChecker>,
+}
+
+impl CheckerPass {
+    pub fn new(checker: Box<dyn BugChecker>) -> Self {
+        Self { checker }
+    }
+}
+
+impl AnalysisPass for CheckerPass {
+    fn name(&self) -> String {
+        format!("[{} Checker]", self.checker.name())
+    }
+
+    fn requires(&self) -> Vec<Artifact> {
+        vec![Artifact::LockSets]
+    }
+
+    fn run_pass<'tcx>(&mut self, my_tcx: &mut MyTcx<'tcx>) {
+        let mut reporter = std::mem::take(&mut my_tcx.reporter);
+        let checked = isolate(|| {
+            self.checker
+                .check(&CheckerContext::new(my_tcx), &mut reporter)
+        });
+        my_tcx.reporter = reporter;
+        if let Err(reason) = checked {
+            my_tcx.fail(format!("{} checker", self.checker.name()), reason);
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use rustc_hir::def_id::DefId;
+use rustc_middle::{
+    mir::{Location, StatementKind, TerminatorKind},
+    ty::{self, CoroutineArgs, CoroutineArgsExt, TyCtxt},
+};
+
+use crate::{
+    analysis::tools::{is_guard, is_mutex_method, lock_api_acquire},
+    report::{Finding, Reporter},
+};
+
+use super::{BugChecker, CheckerContext};
+
+/// report blocking guards, of `std::sync` or parking_lot, held across an await,
+/// which block the executor's thread while the task is parked
+pub struct AwaitLockChecker;
+
+impl BugChecker for AwaitLockChecker {
+    fn name(&self) -> String {
+        "Await Holding Lock".to_string()
+    }
+
+    fn check<'tcx>(&mut self, cx: &CheckerContext<'_, 'tcx>, reporter: &mut Reporter) {
+        let tcx = cx.tcx();
+        for def_id in cx.functions() {
+            if !tcx.is_coroutine(def_id) {
+                continue;
+            }
+            let body = cx.body(def_id);
+            for (bb, data) in body.basic_blocks.iter_enumerated() {
+                if cx.lock_facts(def_id, bb).is_none() {
+                    continue;
+                }
+                // a suspension stores its state in the coroutine before returning `Pending`,
+                // the first variants are unresumed, returned and poisoned
+                for (index, statement) in data.statements.iter().enumerate() {
+                    let StatementKind::SetDiscriminant {
+                        place,
+                        variant_index,
+                    } = &statement.kind
+                    else {
+                        continue;
+                    };
+                    // the state is often reached through a copy of `_1`, e.g. `(*_18)`
+                    let is_state = matches!(
+                        place.ty(body, tcx).ty.kind(),
+                        ty::Coroutine(coroutine, _) if *coroutine == def_id
+                    );
+                    if !is_state || variant_index.as_usize() < CoroutineArgs::RESERVED_VARIANTS {
+                        continue;
+                    }
+                    let location = Location {
+                        block: bb,
+                        statement_index: index,
+                    };
+                    let held: Vec<_> = cx
+                        .held_locks(def_id, location)
+                        .into_iter()
+                        .filter_map(|lock| {
+                            let site = cx.acquisition_site(def_id, location, &lock)?;
+                            is_sync_lock(cx, tcx, site).then_some((lock, site))
+                        })
+                        .collect();
+                    if held.is_empty() {
+                        continue;
+                    }
+                    let mut finding = Finding::new(
+                        "await-lock",
+                        format!(
+                            "{} awaits while holding {} blocking lock(s)",
+                            tcx.def_path_str(def_id),
+                            held.len()
+                        ),
+                    )
+                    .within(def_id)
+                    .with_trace(format!(
+                        "{}: awaits",
+                        cx.describe_span(statement.source_info.span)
+                    ));
+                    for (lock, (site_def_id, site)) in held.iter() {
+                        finding = finding.with_trace(format!(
+                            "{}: {} acquired",
+                            cx.describe_location(*site_def_id, *site),
+                            cx.describe_lock(lock)
+                        ));
+                    }
+                    reporter.report(finding);
+                }
+            }
+        }
+    }
+}
+
+/// whether a lock was acquired by a blocking call, rather than an async lock,
+/// or by a helper returning a blocking guard
+fn is_sync_lock<'tcx>(
+    cx: &CheckerContext<'_, 'tcx>,
+    tcx: TyCtxt<'tcx>,
+    (def_id, location): (DefId, Location),
+) -> bool {
+    let Some(callee) = cx.callee_at(def_id, location) else {
+        return false;
+    };
+    let path = tcx.def_path_str(callee);
+    if is_mutex_method(&path) || lock_api_acquire(&path).is_some() {
+        return true;
+    }
+    let body = cx.body(def_id);
+    match &body.basic_blocks[location.block].terminator().kind {
+        TerminatorKind::Call { destination, .. } => is_guard(&destination.ty(body, tcx).ty),
+        _ => false,
+    }
+}
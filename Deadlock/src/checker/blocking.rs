@@ -0,0 +1,166 @@
+use rustc_hash::FxHashMap;
+use rustc_hir::def_id::DefId;
+use rustc_middle::{
+    mir::{Operand, TerminatorKind},
+    ty::{self, TyCtxt},
+};
+
+use crate::{
+    analysis::tools::normalize_path,
+    report::{Finding, Reporter},
+};
+
+use super::{BugChecker, CheckerContext};
+
+/// functions known to block, matched as def path prefixes
+/// condvar waits are left to the condvar checker
+const BLOCKING_FNS: [&str; 11] = [
+    "std::thread::sleep",
+    "std::thread::JoinHandle::join",
+    "std::thread::ScopedJoinHandle::join",
+    "std::sync::mpsc::Receiver::recv",
+    "std::sync::mpsc::SyncSender::send",
+    "std::sync::Barrier::wait",
+    "std::fs::",
+    "std::net::TcpStream::",
+    "std::net::TcpListener::",
+    "std::net::UdpSocket::",
+    "std::io::Stdin::read",
+];
+
+/// report locks held across calls that may block
+pub struct BlockingChecker {
+    patterns: Vec<String>,
+    // local functions that may block, with the call chain to the blocking one
+    blocking: FxHashMap<DefId, Vec<String>>,
+}
+
+impl BlockingChecker {
+    pub fn new(extra: &[String]) -> Self {
+        let mut patterns: Vec<String> = BLOCKING_FNS.iter().map(|p| p.to_string()).collect();
+        patterns.extend(extra.iter().cloned());
+        Self {
+            patterns,
+            blocking: FxHashMap::default(),
+        }
+    }
+
+    fn is_blocking(&self, path: &str) -> bool {
+        self.patterns.iter().any(|p| path.starts_with(p.as_str()))
+    }
+
+    /// the call chain from a callee to a blocking function, if any
+    fn blocking_chain<'tcx>(
+        &self,
+        tcx: TyCtxt<'tcx>,
+        func: &Operand<'tcx>,
+    ) -> Option<Vec<String>> {
+        let Operand::Constant(constant) = func else {
+            return None;
+        };
+        let ty::FnDef(callee, args) = constant.ty().kind() else {
+            return None;
+        };
+        if let Some(chain) = self.blocking.get(callee) {
+            let mut chain = chain.clone();
+            chain.insert(0, tcx.def_path_str(*callee));
+            return Some(chain);
+        }
+        let mut path = normalize_path(&tcx.def_path_str(*callee));
+        // a trait method blocks depending on the implementing type
+        if tcx.trait_of_item(*callee).is_some() {
+            if let Some(self_ty) = args.types().next() {
+                let self_ty = normalize_path(&self_ty.peel_refs().to_string());
+                let name = tcx.item_name(*callee);
+                if self.is_blocking(&format!("{}::{}", self_ty, name)) {
+                    path = format!("{}::{}", self_ty, name);
+                }
+            }
+        }
+        if self.is_blocking(&path) {
+            Some(vec![path])
+        } else {
+            None
+        }
+    }
+}
+
+impl BugChecker for BlockingChecker {
+    fn name(&self) -> String {
+        "Blocking Call".to_string()
+    }
+
+    fn check<'tcx>(&mut self, cx: &CheckerContext<'_, 'tcx>, reporter: &mut Reporter) {
+        let tcx = cx.tcx();
+        // callees come first, so whether a local callee blocks is known at its call sites
+        for def_id in cx.call_graph().topo.clone() {
+            if !cx.is_analyzed(def_id) {
+                continue;
+            }
+            let body = cx.body(def_id);
+            for (bb, data) in body.basic_blocks.iter_enumerated() {
+                if cx.lock_facts(def_id, bb).is_none() {
+                    continue;
+                }
+                let TerminatorKind::Call { func, .. } = &data.terminator().kind else {
+                    continue;
+                };
+                let Some(chain) = self.blocking_chain(tcx, func) else {
+                    continue;
+                };
+                let location = body.terminator_loc(bb);
+                let held = cx.held_locks(def_id, location);
+                if !held.is_empty() {
+                    let mut finding = Finding::new(
+                        "blocking",
+                        format!(
+                            "{} may block while holding {} lock(s) in {}",
+                            chain.last().unwrap(),
+                            held.len(),
+                            tcx.def_path_str(def_id)
+                        ),
+                    )
+                    .within(def_id)
+                    .with_trace(format!(
+                        "{}: calls {}",
+                        cx.describe_location(def_id, location),
+                        chain.join(" -> ")
+                    ));
+                    for lock in held.iter() {
+                        finding =
+                            finding.with_trace(format!("holding {}", cx.describe_lock(lock)));
+                    }
+                    reporter.report(finding);
+                }
+                self.blocking.entry(def_id).or_insert(chain);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(
+            normalize_path("std::thread::JoinHandle::<T>::join"),
+            "std::thread::JoinHandle::join"
+        );
+        assert_eq!(
+            normalize_path("<std::fs::File as std::io::Read>::read"),
+            "std::fs::File::read"
+        );
+        assert_eq!(normalize_path("std::thread::sleep"), "std::thread::sleep");
+    }
+
+    #[test]
+    fn test_is_blocking() {
+        let checker = BlockingChecker::new(&["my_crate::rpc::call".to_string()]);
+        assert!(checker.is_blocking("std::sync::Barrier::wait"));
+        assert!(checker.is_blocking("std::fs::File::open"));
+        assert!(checker.is_blocking("my_crate::rpc::call"));
+        assert!(!checker.is_blocking("std::sync::Mutex::lock"));
+    }
+}
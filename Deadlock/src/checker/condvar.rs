@@ -0,0 +1,184 @@
+use rustc_hash::{FxHashMap, FxHashSet};
+use rustc_middle::mir::{BasicBlock, Body};
+
+use crate::{
+    analysis::{
+        condvar::{CondvarOp, CondvarOpKind},
+        lock::Lock,
+    },
+    report::{Finding, Reporter},
+};
+
+use super::{BugChecker, CheckerContext};
+
+/// report waits without a loop, condvars used with several mutexes,
+/// waits holding other locks and waits nobody notifies
+pub struct CondvarChecker;
+
+impl BugChecker for CondvarChecker {
+    fn name(&self) -> String {
+        "Condvar".to_string()
+    }
+
+    fn check<'tcx>(&mut self, cx: &CheckerContext<'_, 'tcx>, reporter: &mut Reporter) {
+        let tcx = cx.tcx();
+        let ops = cx.condvar_ops();
+        let (condvars, mutexes) = classes(ops);
+
+        let mut notified = FxHashSet::default();
+        // condvar -> mutex -> the first wait pairing them
+        let mut paired: FxHashMap<Lock, FxHashMap<Lock, usize>> = FxHashMap::default();
+        for (index, op) in ops.iter().enumerate() {
+            let Some(condvar) = op.condvar.first().map(|lock| condvars.find(lock)) else {
+                continue;
+            };
+            if !op.kind.is_wait() {
+                notified.insert(condvar);
+            } else if let Some(mutex) = op.mutex.first().map(|lock| mutexes.find(lock)) {
+                paired
+                    .entry(condvar)
+                    .or_default()
+                    .entry(mutex)
+                    .or_insert(index);
+            }
+        }
+
+        for op in ops.iter().filter(|op| op.origin.is_none() && op.kind.is_wait()) {
+            let (def_id, location) = op.site;
+            let site = cx.describe_location(def_id, location);
+            if op.kind != CondvarOpKind::WaitWhile && !in_loop(cx.body(def_id), location.block)
+            {
+                reporter.report(
+                    Finding::new(
+                        "condvar",
+                        format!(
+                            "condvar wait outside a loop re-checking the predicate in {}",
+                            tcx.def_path_str(def_id)
+                        ),
+                    )
+                    .within(def_id)
+                    .with_trace(format!("{}: spurious or lost wakeups are not handled", site)),
+                );
+            }
+
+            let others: Vec<Lock> = cx
+                .held_locks(def_id, location)
+                .into_iter()
+                .filter(|lock| !op.mutex.contains(lock))
+                .collect();
+            if !others.is_empty() {
+                let mut finding = Finding::new(
+                    "condvar",
+                    format!(
+                        "condvar wait holding {} other lock(s) in {}",
+                        others.len(),
+                        tcx.def_path_str(def_id)
+                    ),
+                )
+                .within(def_id)
+                .with_trace(format!("{}: waits", site));
+                for lock in others.iter() {
+                    finding = finding.with_trace(format!("holding {}", cx.describe_lock(lock)));
+                }
+                reporter.report(finding);
+            }
+
+            let notifiable = op
+                .condvar
+                .first()
+                .map_or(true, |lock| notified.contains(&condvars.find(lock)));
+            if !notifiable {
+                reporter.report(
+                    Finding::new(
+                        "condvar",
+                        format!(
+                            "no notify reaches the condvar waited on in {}",
+                            tcx.def_path_str(def_id)
+                        ),
+                    )
+                    .within(def_id)
+                    .with_trace(format!("{}: waits forever", site)),
+                );
+            }
+        }
+
+        for waits in paired.values().filter(|waits| waits.len() > 1) {
+            let mut finding = Finding::new(
+                "condvar",
+                format!("a condvar is used with {} different mutexes", waits.len()),
+            );
+            for wait in waits.values() {
+                let op = &ops[*wait];
+                let (def_id, location) = op.site;
+                finding = finding.within(def_id).with_trace(format!(
+                    "{}: waits with {}",
+                    cx.describe_location(def_id, location),
+                    cx.describe_lock(&op.mutex[0])
+                ));
+            }
+            reporter.report(finding);
+        }
+    }
+}
+
+/// the same condvar or mutex has a name in every function it is used in,
+/// the names are joined through the copies of the operations
+fn classes(ops: &[CondvarOp]) -> (UnionFind, UnionFind) {
+    let mut condvars = UnionFind::default();
+    let mut mutexes = UnionFind::default();
+    for op in ops.iter() {
+        for locks in op.condvar.windows(2) {
+            condvars.union(&locks[0], &locks[1]);
+        }
+        for locks in op.mutex.windows(2) {
+            mutexes.union(&locks[0], &locks[1]);
+        }
+        if let Some(origin) = op.origin.map(|origin| &ops[origin]) {
+            if let (Some(a), Some(b)) = (op.condvar.first(), origin.condvar.first()) {
+                condvars.union(a, b);
+            }
+            if let (Some(a), Some(b)) = (op.mutex.first(), origin.mutex.first()) {
+                mutexes.union(a, b);
+            }
+        }
+    }
+    (condvars, mutexes)
+}
+
+/// whether a bb can reach itself
+pub(super) fn in_loop(body: &Body<'_>, bb: BasicBlock) -> bool {
+    let mut visited = FxHashSet::default();
+    let mut work_list: Vec<BasicBlock> =
+        body.basic_blocks[bb].terminator().successors().collect();
+    while let Some(current) = work_list.pop() {
+        if current == bb {
+            return true;
+        }
+        if visited.insert(current) {
+            work_list.extend(body.basic_blocks[current].terminator().successors());
+        }
+    }
+    false
+}
+
+#[derive(Default)]
+struct UnionFind {
+    parent: FxHashMap<Lock, Lock>,
+}
+
+impl UnionFind {
+    fn find(&self, lock: &Lock) -> Lock {
+        let mut current = lock.clone();
+        while let Some(parent) = self.parent.get(&current) {
+            current = parent.clone();
+        }
+        current
+    }
+
+    fn union(&mut self, a: &Lock, b: &Lock) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a != b {
+            self.parent.insert(a, b);
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use rustc_middle::mir::TerminatorKind;
+
+use crate::{
+    analysis::tools::{callee_def_id, is_thread_join},
+    report::{Finding, Reporter},
+};
+
+use super::{BugChecker, CheckerContext};
+
+/// report threads joined while holding a lock they may acquire
+pub struct JoinChecker;
+
+impl BugChecker for JoinChecker {
+    fn name(&self) -> String {
+        "Join".to_string()
+    }
+
+    fn check<'tcx>(&mut self, cx: &CheckerContext<'_, 'tcx>, reporter: &mut Reporter) {
+        let tcx = cx.tcx();
+        for def_id in cx.functions() {
+            let body = cx.body(def_id);
+            for (bb, data) in body.basic_blocks.iter_enumerated() {
+                if cx.lock_facts(def_id, bb).is_none() {
+                    continue;
+                }
+                let TerminatorKind::Call { func, args, .. } = &data.terminator().kind else {
+                    continue;
+                };
+                let Some(callee) = callee_def_id(func) else {
+                    continue;
+                };
+                if !is_thread_join(&tcx.def_path_str(callee)) {
+                    continue;
+                }
+                let Some(handle) = args.first().and_then(|arg| arg.node.place()) else {
+                    continue;
+                };
+                let location = body.terminator_loc(bb);
+                let held = cx.held_locks(def_id, location);
+                if held.is_empty() {
+                    continue;
+                }
+                for thread in cx.joined_threads(def_id, &handle) {
+                    for (lock, thread_site) in thread.acquires.iter() {
+                        if !held.contains(lock) {
+                            continue;
+                        }
+                        let mut finding = Finding::new(
+                            "join",
+                            format!(
+                                "{} joins a thread that may acquire {} while holding it",
+                                tcx.def_path_str(def_id),
+                                cx.describe_lock(lock)
+                            ),
+                        )
+                        .within(def_id)
+                        .within(thread.closure)
+                        .with_trace(format!(
+                            "{}: thread spawned",
+                            cx.describe_location(thread.site.0, thread.site.1)
+                        ));
+                        if let Some((site_def_id, site)) =
+                            cx.acquisition_site(def_id, location, lock)
+                        {
+                            finding = finding.with_trace(format!(
+                                "{}: acquired by the joining thread",
+                                cx.describe_location(site_def_id, site)
+                            ));
+                        }
+                        finding = match thread_site {
+                            Some((site_def_id, site)) => finding.with_trace(format!(
+                                "{}: acquired by the spawned thread",
+                                cx.describe_location(*site_def_id, *site)
+                            )),
+                            None => finding.with_trace(format!(
+                                "acquired by the spawned thread in a callee of {}",
+                                tcx.def_path_str(thread.closure)
+                            )),
+                        };
+                        finding = finding.with_trace(format!(
+                            "{}: joined while holding the lock",
+                            cx.describe_location(def_id, location)
+                        ));
+                        reporter.report(finding);
+                    }
+                }
+            }
+        }
+    }
+}
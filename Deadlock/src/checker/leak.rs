@@ -0,0 +1,65 @@
+use rustc_middle::mir::TerminatorKind;
+
+use crate::report::{Finding, Reporter};
+
+use super::{BugChecker, CheckerContext};
+
+/// report locks released on the normal path but still held when a panic unwinds out
+/// of the function, e.g. a raw lock whose `unlock` is skipped by the panic
+pub struct LeakChecker;
+
+impl BugChecker for LeakChecker {
+    fn name(&self) -> String {
+        "Unwind Leak".to_string()
+    }
+
+    fn check<'tcx>(&mut self, cx: &CheckerContext<'_, 'tcx>, reporter: &mut Reporter) {
+        let tcx = cx.tcx();
+        for def_id in cx.functions() {
+            let body = cx.body(def_id);
+            for (bb, data) in body.basic_blocks.iter_enumerated() {
+                if !matches!(data.terminator().kind, TerminatorKind::UnwindResume)
+                    || cx.lock_facts(def_id, bb).is_none()
+                {
+                    continue;
+                }
+                let location = body.terminator_loc(bb);
+                for lock in cx.held_locks(def_id, location) {
+                    // a lock never released in the function is handed over on purpose
+                    let released = body.basic_blocks.iter_enumerated().any(|(other, data)| {
+                        !data.is_cleanup
+                            && cx.lock_facts(def_id, other).is_some_and(|facts| {
+                                facts.iter().flatten().any(|fact| {
+                                    !fact.is_acquisition && fact.state && fact.lock == lock
+                                })
+                            })
+                    });
+                    if !released {
+                        continue;
+                    }
+                    let mut finding = Finding::new(
+                        "unwind-leak",
+                        format!(
+                            "{} stays locked when a panic unwinds out of {}",
+                            cx.describe_lock(&lock),
+                            tcx.def_path_str(def_id)
+                        ),
+                    )
+                    .within(def_id);
+                    if let Some((site_def_id, site)) = cx.acquisition_site(def_id, location, &lock)
+                    {
+                        finding = finding.with_trace(format!(
+                            "{}: acquired",
+                            cx.describe_location(site_def_id, site)
+                        ));
+                    }
+                    finding = finding.with_trace(format!(
+                        "{}: unwinds without releasing it",
+                        cx.describe_location(def_id, location)
+                    ));
+                    reporter.report(finding);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,29 @@
+use crate::report::Reporter;
+
+use super::{BugChecker, CheckerContext};
+
+/// report locks acquired again while being held
+pub struct DoubleLockChecker;
+
+impl BugChecker for DoubleLockChecker {
+    fn name(&self) -> String {
+        "Double Lock".to_string()
+    }
+
+    fn check<'tcx>(&mut self, cx: &CheckerContext<'_, 'tcx>, reporter: &mut Reporter) {
+        cx.lock_graph().report_self_loops(cx.tcx(), reporter);
+    }
+}
+
+/// report locks acquired in a cyclic order
+pub struct LockOrderChecker;
+
+impl BugChecker for LockOrderChecker {
+    fn name(&self) -> String {
+        "Lock Order".to_string()
+    }
+
+    fn check<'tcx>(&mut self, cx: &CheckerContext<'_, 'tcx>, reporter: &mut Reporter) {
+        cx.lock_graph().report_cycles(cx.tcx(), reporter);
+    }
+}
@@ -0,0 +1,237 @@
+use rustc_hash::FxHashSet;
+use rustc_hir::def_id::DefId;
+use rustc_middle::{
+    mir::{AssertKind, Body, Location, TerminatorKind, UnwindAction},
+    ty::TyCtxt,
+};
+
+use crate::{
+    analysis::{
+        lock::Lock,
+        tools::{callee_def_id, is_mutex_method, normalize_path},
+    },
+    report::{Finding, Reporter},
+};
+
+use super::{condvar::in_loop, BugChecker, CheckerContext};
+
+type StatementSite = (DefId, Location);
+
+/// report panics in the critical sections of a `std::sync::Mutex` poisoning it,
+/// while the mutex is acquired with `.lock().unwrap()` elsewhere, which then panics too;
+/// a victim is reported once for a lock, with the first panic found poisoning it
+pub struct PoisonChecker;
+
+impl BugChecker for PoisonChecker {
+    fn name(&self) -> String {
+        "Lock Poisoning".to_string()
+    }
+
+    fn check<'tcx>(&mut self, cx: &CheckerContext<'_, 'tcx>, reporter: &mut Reporter) {
+        let tcx = cx.tcx();
+        let unwrapped = unwrapped_lock_sites(cx);
+        let mut reported: FxHashSet<(Lock, StatementSite)> = FxHashSet::default();
+        for def_id in cx.functions() {
+            let body = cx.body(def_id);
+            for (bb, data) in body.basic_blocks.iter_enumerated() {
+                if data.is_cleanup || cx.lock_facts(def_id, bb).is_none() {
+                    continue;
+                }
+                let Some((what, UnwindAction::Cleanup(cleanup))) =
+                    may_panic(tcx, body, &data.terminator().kind)
+                else {
+                    continue;
+                };
+                // the guard is dropped while unwinding, which poisons its mutex
+                if cx.lock_facts(def_id, cleanup).is_none() {
+                    continue;
+                }
+                let location = body.terminator_loc(bb);
+                for lock in cx.held_locks(def_id, location) {
+                    let Some(site) = cx.acquisition_site(def_id, location, &lock) else {
+                        continue;
+                    };
+                    if !cx
+                        .callee_at(site.0, site.1)
+                        .is_some_and(|callee| is_mutex_method(&tcx.def_path_str(callee)))
+                    {
+                        continue;
+                    }
+                    let victims: Vec<StatementSite> = poisoned_acquisitions(cx, def_id, &lock, site)
+                        .into_iter()
+                        .filter(|victim| unwrapped.contains(victim))
+                        .filter(|victim| reported.insert((lock.clone(), *victim)))
+                        .collect();
+                    if victims.is_empty() {
+                        continue;
+                    }
+                    let mut finding = Finding::new(
+                        "poison",
+                        format!(
+                            "{} in a critical section of {} poisons the mutex, \
+                             which is unwrapped when locked elsewhere",
+                            what,
+                            tcx.def_path_str(def_id)
+                        ),
+                    )
+                    .within(def_id)
+                    .with_trace(format!(
+                        "{}: {} acquired",
+                        cx.describe_location(site.0, site.1),
+                        cx.describe_lock(&lock)
+                    ))
+                    .with_trace(format!("{}: may panic", cx.describe_location(def_id, location)));
+                    for (victim_def_id, victim) in victims {
+                        finding = finding.with_trace(format!(
+                            "{}: `.lock().unwrap()` panics on the poisoned mutex",
+                            cx.describe_location(victim_def_id, victim)
+                        ));
+                    }
+                    finding = finding.with_trace(String::from(
+                        "recover with `.lock().unwrap_or_else(PoisonError::into_inner)` \
+                         if the protected data stays consistent",
+                    ));
+                    reporter.report(finding);
+                }
+            }
+        }
+    }
+}
+
+/// what may panic at a terminator, and where unwinding goes
+fn may_panic<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    body: &Body<'tcx>,
+    kind: &TerminatorKind<'tcx>,
+) -> Option<(&'static str, UnwindAction)> {
+    match kind {
+        TerminatorKind::Assert { msg, unwind, .. } => {
+            let what = match **msg {
+                AssertKind::BoundsCheck { .. } => "an index out of bounds",
+                AssertKind::Overflow(..)
+                | AssertKind::OverflowNeg(_)
+                | AssertKind::DivisionByZero(_)
+                | AssertKind::RemainderByZero(_) => "an arithmetic overflow",
+                _ => "a failed assertion",
+            };
+            Some((what, *unwind))
+        }
+        TerminatorKind::Call {
+            func, args, unwind, ..
+        } => {
+            let path = normalize_path(&tcx.def_path_str(callee_def_id(func)?));
+            let what = if path.starts_with("core::panicking::")
+                || path.starts_with("std::panicking::begin_panic")
+                || path.starts_with("std::rt::begin_panic")
+            {
+                "a panic"
+            } else if ["Option::unwrap", "Option::expect", "Result::unwrap", "Result::expect"]
+                .iter()
+                .any(|method| path.ends_with(method))
+            {
+                // unwrapping the result of a lock is the victim, not the cause,
+                // and a failed try-lock holds nothing
+                let unwrapped = args.first()?.node.ty(body, tcx).to_string();
+                if unwrapped.contains("PoisonError") || unwrapped.contains("TryLockError") {
+                    return None;
+                }
+                "an unwrap"
+            } else if path.ends_with("::index") || path.ends_with("::index_mut") {
+                "an index out of bounds"
+            } else {
+                return None;
+            };
+            Some((what, *unwind))
+        }
+        _ => None,
+    }
+}
+
+/// the `Mutex::lock` calls whose result is unwrapped or expected
+fn unwrapped_lock_sites(cx: &CheckerContext<'_, '_>) -> FxHashSet<StatementSite> {
+    let tcx = cx.tcx();
+    let mut sites = FxHashSet::default();
+    for def_id in cx.functions() {
+        let body = cx.body(def_id);
+        let mut results = vec![];
+        for (bb, data) in body.basic_blocks.iter_enumerated() {
+            if let TerminatorKind::Call {
+                func, destination, ..
+            } = &data.terminator().kind
+            {
+                let Some(callee) = callee_def_id(func) else {
+                    continue;
+                };
+                let path = tcx.def_path_str(callee);
+                if is_mutex_method(&path) && tcx.item_name(callee).as_str() == "lock" {
+                    results.push((destination.local, body.terminator_loc(bb)));
+                }
+            }
+        }
+        for data in body.basic_blocks.iter() {
+            let TerminatorKind::Call { func, args, .. } = &data.terminator().kind else {
+                continue;
+            };
+            let Some(callee) = callee_def_id(func) else {
+                continue;
+            };
+            let name = tcx.item_name(callee);
+            if name.as_str() != "unwrap" && name.as_str() != "expect" {
+                continue;
+            }
+            let Some(place) = args.first().and_then(|arg| arg.node.place()) else {
+                continue;
+            };
+            for (result, location) in results.iter() {
+                if place.local == *result && place.projection.is_empty() {
+                    sites.insert((def_id, *location));
+                }
+            }
+        }
+    }
+    sites
+}
+
+/// the acquisitions of a lock that fail once a panic poisons it: the others in the
+/// function, and through the spawner, the ones of every thread sharing it
+fn poisoned_acquisitions(
+    cx: &CheckerContext<'_, '_>,
+    def_id: DefId,
+    lock: &Lock,
+    site: StatementSite,
+) -> Vec<StatementSite> {
+    let mut acquisitions = vec![];
+    let mut push = |acquisition: StatementSite, repeated: bool| {
+        if (repeated || acquisition != site) && !acquisitions.contains(&acquisition) {
+            acquisitions.push(acquisition);
+        }
+    };
+    for acquisition in cx.acquisitions(def_id, lock) {
+        push(acquisition, false);
+    }
+    let threads = cx.threads();
+    for thread in threads.iter().filter(|thread| thread.closure == def_id) {
+        let Some((shared, _)) = thread
+            .acquires
+            .iter()
+            .find(|(_, acquired)| *acquired == Some(site))
+        else {
+            continue;
+        };
+        // a thread pool runs the same closure in other threads, which are victims too
+        let (spawner, spawn_site) = thread.site;
+        let repeated = in_loop(cx.body(spawner), spawn_site.block)
+            || threads.iter().filter(|other| other.closure == def_id).count() > 1;
+        for other in threads.iter().filter(|other| other.site.0 == spawner) {
+            for (acquired, acquisition) in other.acquires.iter() {
+                if let (true, Some(acquisition)) = (acquired == shared, acquisition) {
+                    push(*acquisition, repeated);
+                }
+            }
+        }
+        for acquisition in cx.acquisitions(spawner, shared) {
+            push(acquisition, false);
+        }
+    }
+    acquisitions
+}
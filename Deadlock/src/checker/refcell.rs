@@ -0,0 +1,54 @@
+use crate::report::{Finding, Reporter};
+
+use super::{BugChecker, CheckerContext};
+
+/// report `RefCell` borrows made while a conflicting `Ref` or `RefMut` of the same cell
+/// is alive, which panic with `BorrowError` or `BorrowMutError`
+pub struct RefCellChecker;
+
+impl BugChecker for RefCellChecker {
+    fn name(&self) -> String {
+        "RefCell Borrow".to_string()
+    }
+
+    fn check<'tcx>(&mut self, cx: &CheckerContext<'_, 'tcx>, reporter: &mut Reporter) {
+        let tcx = cx.tcx();
+        for op in cx.borrow_ops() {
+            for held in op.conflicts() {
+                let (kind, held_kind) = match (op.mutable, held.mutable) {
+                    (true, true) => ("borrow_mut", "mutably borrowed"),
+                    (true, false) => ("borrow_mut", "borrowed"),
+                    _ => ("borrow", "mutably borrowed"),
+                };
+                let mut finding = Finding::new(
+                    "refcell",
+                    format!(
+                        "{} of a RefCell already {} panics in {}",
+                        kind,
+                        held_kind,
+                        tcx.def_path_str(op.owner)
+                    ),
+                )
+                .within(op.owner)
+                .with_trace(format!(
+                    "{}: {}",
+                    cx.describe_location(held.site.0, held.site.1),
+                    held_kind
+                ));
+                if let Some((def_id, location)) = op.via {
+                    finding = finding.with_trace(format!(
+                        "{}: calls {}",
+                        cx.describe_location(def_id, location),
+                        tcx.def_path_str(op.site.0)
+                    ));
+                }
+                finding = finding.with_trace(format!(
+                    "{}: {} again",
+                    cx.describe_location(op.site.0, op.site.1),
+                    kind
+                ));
+                reporter.report(finding);
+            }
+        }
+    }
+}
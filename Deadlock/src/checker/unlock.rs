@@ -0,0 +1,67 @@
+use crate::report::{Finding, Reporter};
+
+use super::{BugChecker, CheckerContext};
+
+/// report explicit unlocks, of raw locks or by `force_unlock`, releasing a lock twice,
+/// or a lock the function acquires only on other paths; a lock the function never
+/// acquires is left to its callers, or to other functions pairing it by hand
+pub struct UnlockChecker;
+
+impl BugChecker for UnlockChecker {
+    fn name(&self) -> String {
+        "Unmatched Unlock".to_string()
+    }
+
+    fn check<'tcx>(&mut self, cx: &CheckerContext<'_, 'tcx>, reporter: &mut Reporter) {
+        let tcx = cx.tcx();
+        for unlock in cx.raw_unlocks() {
+            let (def_id, location) = unlock.site;
+            let finding = if unlock.is_double() {
+                let mut finding = Finding::new(
+                    "double-unlock",
+                    format!(
+                        "{} may be released twice in {}",
+                        cx.describe_lock(&unlock.lock),
+                        tcx.def_path_str(def_id)
+                    ),
+                )
+                .within(def_id);
+                for (released_def_id, released) in unlock.released.iter() {
+                    finding = finding.with_trace(format!(
+                        "{}: released",
+                        cx.describe_location(*released_def_id, *released)
+                    ));
+                }
+                finding.with_trace(format!(
+                    "{}: released again",
+                    cx.describe_location(def_id, location)
+                ))
+            } else {
+                let acquisitions = cx.acquisitions(def_id, &unlock.lock);
+                if !unlock.is_unmatched() || acquisitions.is_empty() {
+                    continue;
+                }
+                let mut finding = Finding::new(
+                    "unmatched-unlock",
+                    format!(
+                        "{} is unlocked in {} without being acquired on the way",
+                        cx.describe_lock(&unlock.lock),
+                        tcx.def_path_str(def_id)
+                    ),
+                )
+                .within(def_id);
+                for (site_def_id, site) in acquisitions {
+                    finding = finding.with_trace(format!(
+                        "{}: acquired on another path",
+                        cx.describe_location(site_def_id, site)
+                    ));
+                }
+                finding.with_trace(format!(
+                    "{}: released without holding it",
+                    cx.describe_location(def_id, location)
+                ))
+            };
+            reporter.report(finding);
+        }
+    }
+}
@@ -2,33 +2,236 @@
 //!
 //!
 
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
 use rustc_hir::{def::DefKind, def_id::DefId};
-use rustc_middle::{mir::BasicBlock, ty::TyCtxt};
+use rustc_middle::{
+    mir::{BasicBlock, Location},
+    ty::TyCtxt,
+};
 use rustc_span::Symbol;
 
 use crate::{
-    analysis::{alias::graph::AliasGraph, callgraph::CallGraph},
+    analysis::{
+        alias::graph::AliasGraph,
+        borrow::{BorrowOp, HeldBorrow},
+        callgraph::CallGraph,
+        condvar::CondvarOp,
+        isolate::SkippedFn,
+        lock::{Lock, LockSummary},
+        lockgraph::LockGraph,
+        prepass::FnPrepass,
+        raw::RawUnlock,
+        summary::SummaryStore,
+        thread::ThreadSpawn,
+    },
     option::Options,
+    report::Reporter,
 };
 
 #[derive(Clone)]
 pub struct MyTcx<'tcx> {
     pub tcx: TyCtxt<'tcx>,
+    pub options: Options,
     pub call_graph: CallGraph<'tcx>,
     pub alias_graph: AliasGraph,
     // the traversing order of bbs in each function
     pub control_flow_graph: FxHashMap<DefId, Vec<BasicBlock>>,
+    // what each function's analyses need from its body alone, computed in parallel
+    pub prepass: FxHashMap<DefId, FnPrepass>,
+    // a DefId + BasicBlock's index pair determines a bb
+    pub lock_set_facts: FxHashMap<DefId, FxHashMap<usize, LockSummary>>,
+    pub lock_graph: LockGraph,
+    // lock summaries of the current crate and its dependencies
+    pub summaries: SummaryStore,
+    pub threads: Vec<ThreadSpawn>,
+    pub condvar_ops: Vec<CondvarOp>,
+    // `RefCell` borrows are tracked as acquisitions, but kept out of the lock sets
+    // and the lock graph; a site maps to whether its borrow is mutable
+    pub borrow_sites: FxHashMap<(DefId, Location), bool>,
+    pub borrow_ops: Vec<BorrowOp>,
+    pub raw_unlocks: Vec<RawUnlock>,
+    pub reporter: Reporter,
+    // functions whose analysis panicked, in the order they failed; their summaries are unknown
+    pub skipped: Vec<SkippedFn>,
+    // analyses that panicked as a whole rather than in one function, with why
+    pub failed: Vec<(String, String)>,
+    // functions calling one whose summary is unknown, directly or through their callees
+    pub incomplete: FxHashSet<DefId>,
 }
 
-unsafe impl<'tcx> Send for MyTcx<'tcx> {}
 impl<'tcx> MyTcx<'tcx> {
-    pub fn new(tcx: TyCtxt<'tcx>) -> Self {
+    pub fn new(tcx: TyCtxt<'tcx>, options: Options) -> Self {
+        let summaries = SummaryStore::new(options.summary_dir());
         Self {
             tcx,
+            options,
             call_graph: CallGraph::new(),
             alias_graph: AliasGraph::new(),
             control_flow_graph: FxHashMap::default(),
+            prepass: FxHashMap::default(),
+            lock_set_facts: FxHashMap::default(),
+            lock_graph: LockGraph::new(),
+            summaries,
+            threads: Vec::new(),
+            condvar_ops: Vec::new(),
+            borrow_sites: FxHashMap::default(),
+            borrow_ops: Vec::new(),
+            raw_unlocks: Vec::new(),
+            reporter: Reporter::new(),
+            skipped: Vec::new(),
+            failed: Vec::new(),
+            incomplete: FxHashSet::default(),
+        }
+    }
+
+    /// record a function an analysis gave up on, unless an earlier one already did
+    pub fn skip(&mut self, def_id: DefId, analysis: &'static str, reason: String) {
+        tracing::warn!(
+            "{} skips {}: {}",
+            analysis,
+            self.tcx.def_path_str(def_id),
+            reason
+        );
+        if !self.is_skipped(&def_id) {
+            self.skipped.push(SkippedFn { def_id, analysis, reason });
+        }
+    }
+
+    /// record an analysis that gave up on the whole crate, e.g. a checker
+    pub fn fail(&mut self, analysis: String, reason: String) {
+        tracing::warn!("{} fails: {}", analysis, reason);
+        self.failed.push((analysis, reason));
+    }
+
+    pub fn is_skipped(&self, def_id: &DefId) -> bool {
+        self.skipped.iter().any(|skipped| skipped.def_id == *def_id)
+    }
+
+    /// list the skipped functions and failed analyses in the report, as the results miss
+    /// what they do
+    pub fn report_skipped(&mut self) {
+        let mut notes = vec![];
+        for (analysis, reason) in self.failed.iter() {
+            notes.push(format!("incomplete analysis: the {} failed: {}", analysis, reason));
+        }
+        if !self.skipped.is_empty() {
+            notes.push(format!(
+                "incomplete analysis: {} function(s) skipped after a failure",
+                self.skipped.len()
+            ));
+        }
+        for skipped in self.skipped.iter() {
+            notes.push(format!(
+                "skipped `{}` in the {}: {}",
+                self.tcx.def_path_str(skipped.def_id),
+                skipped.analysis,
+                skipped.reason
+            ));
+        }
+        for note in notes {
+            self.reporter.note(note);
+        }
+    }
+
+    /// locks held at the terminator of a bb, including the ones acquired by the terminator itself
+    pub fn held_locks(&self, def_id: &DefId, bb_index: usize) -> Vec<Lock> {
+        let mut held = vec![];
+        if let Some(summary) = self
+            .lock_set_facts
+            .get(def_id)
+            .and_then(|facts| facts.get(&bb_index))
+        {
+            for lock_set_fact in summary {
+                for lock_fact in lock_set_fact {
+                    if lock_fact.is_acquisition
+                        && !lock_fact.state
+                        && !held.contains(&lock_fact.lock)
+                        && !self.borrow_sites.contains_key(&lock_fact.s_location)
+                    {
+                        held.push(lock_fact.lock.clone());
+                    }
+                }
+            }
+        }
+        held
+    }
+
+    /// locks acquired on some path to the terminator of a bb, released or not
+    pub fn acquired_locks(&self, def_id: &DefId, bb_index: usize) -> Vec<Lock> {
+        let mut acquired = vec![];
+        if let Some(summary) = self
+            .lock_set_facts
+            .get(def_id)
+            .and_then(|facts| facts.get(&bb_index))
+        {
+            for lock_set_fact in summary {
+                for lock_fact in lock_set_fact {
+                    if lock_fact.is_acquisition
+                        && !acquired.contains(&lock_fact.lock)
+                        && !self.borrow_sites.contains_key(&lock_fact.s_location)
+                    {
+                        acquired.push(lock_fact.lock.clone());
+                    }
+                }
+            }
+        }
+        acquired
+    }
+
+    /// `RefCell` borrows alive at the terminator of a bb
+    pub fn held_borrows(&self, def_id: &DefId, bb_index: usize) -> Vec<HeldBorrow> {
+        let mut held = vec![];
+        if let Some(summary) = self
+            .lock_set_facts
+            .get(def_id)
+            .and_then(|facts| facts.get(&bb_index))
+        {
+            for lock_set_fact in summary {
+                for lock_fact in lock_set_fact {
+                    if !lock_fact.is_acquisition || lock_fact.state {
+                        continue;
+                    }
+                    let Some(mutable) = self.borrow_sites.get(&lock_fact.s_location) else {
+                        continue;
+                    };
+                    let borrow = HeldBorrow {
+                        cell: lock_fact.lock.clone(),
+                        mutable: *mutable,
+                        site: lock_fact.s_location,
+                    };
+                    if !held.contains(&borrow) {
+                        held.push(borrow);
+                    }
+                }
+            }
+        }
+        held
+    }
+
+    pub fn print_lock_set_facts(&self) {
+        for (def_id, summaries) in &self.lock_set_facts {
+            println!("DefId: {:?}", def_id);
+            let mut keys: Vec<usize> = summaries.keys().cloned().collect();
+            keys.sort();
+            for index in keys {
+                let summary = summaries.get(&index).unwrap();
+                println!("  Index: {}", index);
+                for (i, lock_set) in summary.iter().enumerate() {
+                    println!("    Lock Summary {:?}:", i);
+                    for lock_fact in lock_set {
+                        let is_acq;
+                        if lock_fact.is_acquisition {
+                            is_acq = "+";
+                        } else {
+                            is_acq = "-";
+                        }
+                        println!(
+                            "      Lock: {:?}, Location: {:?}, {:?}, {:?}",
+                            lock_fact.lock, lock_fact.s_location, is_acq, lock_fact.state as i32
+                        );
+                    }
+                }
+            }
         }
     }
 }
@@ -1,11 +1,13 @@
 use clap::Parser;
-use rustc_compat::{CrateFilter, Plugin, RustcPluginArgs, Utf8Path};
+use rustc_compat::{CrateFilter, Plugin, RustcPluginArgs, Utf8Path, PLUGIN_ARGS};
 use rustc_driver::Compilation;
 use rustc_errors::registry;
 use rustc_hash::FxHashMap;
+use rustc_hir::def_id::LOCAL_CRATE;
 
 use rustc_middle::ty::TyCtxt;
 use rustc_session::config;
+use rustc_span::Symbol;
 use std::{
     borrow::Cow,
     env,
@@ -20,59 +22,39 @@ use crate::{
     analysis::{
         alias::AliasAnalysis,
         callgraph::{CallGraph, CallGraphPass},
+        summary::SummaryStore,
         LockSetAnalysis,
     },
+    cache::{self, CacheEntry},
+    checker::{BugChecker, CheckerPass},
     context::MyTcx,
     option::Options,
+    pass::{builtin_strategies, Strategy, STRATEGIES},
     utils::{
         self,
         mir::{Display, ShowMir},
     },
 };
 
-/// a strategy consists of all necessary passes
-struct Strategy {
-    name: String,
-    passes: Vec<Box<dyn AnalysisPass>>,
-}
-
-impl<'tcx> Strategy {
-    fn new(name: &str) -> Self {
-        Self {
-            name: String::from(name),
-            passes: Vec::new(),
-        }
-    }
-
-    /// register pass in a strategy
-    fn register_pass(&mut self, pass: Box<dyn AnalysisPass>) {
-        self.passes.push(pass);
-    }
-}
-
-pub(crate) trait AnalysisPass: Send {
-    fn name(&self) -> String;
-
-    fn before_run(&mut self) {
-        tracing::info!("{} analysis is running.", self.name());
-    }
-
-    fn run_pass(&mut self) {}
-
-    fn after_run(&mut self) {}
-}
-
 pub(crate) struct MyCallBacks {
     options: Options,
     strategy: FxHashMap<String, Strategy>,
 }
 
 impl MyCallBacks {
-    pub(crate) fn new(options: &Options) -> Self {
-        Self {
+    pub(crate) fn new(options: &Options, checkers: Vec<Box<dyn BugChecker>>) -> Self {
+        let mut callbacks = Self {
             options: options.clone(),
             strategy: FxHashMap::default(),
+        };
+        callbacks.register_strategy();
+        // custom checkers only run in the selected strategy
+        if let Some(stra) = callbacks.strategy.get_mut(&options.strategy) {
+            for checker in checkers {
+                stra.register_pass(Box::new(CheckerPass::new(checker)));
+            }
         }
+        callbacks
     }
 
     /// print
@@ -91,26 +73,17 @@ impl MyCallBacks {
     }
 
     /// register strategies
-    fn register_strategy(&mut self, my_tcx: MyTcx) {
-        let tcx_boxed = Box::new(my_tcx);
-        let my_tcx = Box::leak(tcx_boxed);
-        let mut strategy = Strategy::new("Call Graph Construction");
-        let pass1 = CallGraphPass::new(my_tcx);
-        // strategy.register_pass(Box::new(pass1));
-        self.add_strategy(strategy);
-
-        // -----------------
-        // TODO: more strategy
-        // FIXME: 如何重用tcx？这里用strategy封装的话，内置数据结构不能用&my_tcx
+    fn register_strategy(&mut self) {
+        for strategy in builtin_strategies(&self.options) {
+            self.add_strategy(strategy);
+        }
     }
 
-    fn run_strategy(&mut self, name: &str) {
+    fn run_strategy(&mut self, name: &str, my_tcx: &mut MyTcx) {
         match self.strategy.get_mut(name) {
             Some(stra) => {
-                for pass in &mut stra.passes {
-                    pass.before_run();
-                    // pass.run_pass(context);
-                    pass.after_run();
+                if let Err(e) = stra.run(my_tcx) {
+                    my_tcx.tcx.dcx().err(e);
                 }
             }
             None => {
@@ -125,48 +98,74 @@ impl MyCallBacks {
 }
 
 impl rustc_driver::Callbacks for MyCallBacks {
+    fn config(&mut self, config: &mut rustc_interface::interface::Config) {
+        // let cargo re-run the driver when the options change, the same way clippy tracks CLIPPY_ARGS
+        let plugin_args = env::var(PLUGIN_ARGS).ok();
+        config.psess_created = Some(Box::new(move |psess| {
+            psess.env_depinfo.get_mut().insert((
+                Symbol::intern(PLUGIN_ARGS),
+                plugin_args.as_deref().map(Symbol::intern),
+            ));
+        }));
+    }
+
     fn after_analysis<'tcx>(
         &mut self,
         _compiler: &rustc_interface::interface::Compiler,
         _queries: &'tcx rustc_interface::Queries<'tcx>,
     ) -> rustc_driver::Compilation {
         _queries.global_ctxt().unwrap().enter(|tcx| {
-            if self.options.emit_mir {
-                let mut show_mir = ShowMir::new(tcx);
-                show_mir.start();
+            let krate = tcx.crate_name(LOCAL_CRATE).to_string();
+            let passes = self
+                .strategy
+                .get(&self.options.strategy)
+                .map(|strategy| strategy.pass_names())
+                .unwrap_or_default();
+            let fingerprint = cache::fingerprint(tcx, &self.options, &passes);
+            let cache_dir = self.options.cache_dir();
+            if let Some(dir) = &cache_dir {
+                cache::touch(dir, tcx);
             }
-
-            let my_tcx = MyTcx::new(tcx);
-
-            // self.register_strategy(my_tcx);
-            // TODO
-            // self.run_strategy();
-
-            let tcx_boxed = Box::new(my_tcx);
-            let my_tcx = Box::leak(tcx_boxed);
-            // call graph pre build pass
-            let mut call_graph_pre_build_pass = CallGraphPass::new(my_tcx);
-            call_graph_pre_build_pass.start();
-
-            if self.options.emit_call_graph {
-                call_graph_pre_build_pass.print_topo();
+            if !self.options.emit_any() {
+                if let Some(entry) = cache_dir
+                    .as_ref()
+                    .and_then(|dir| cache::load(dir, tcx, &fingerprint))
+                {
+                    // the summary file may be gone with a `cargo clean`
+                    let mut summaries = SummaryStore::new(self.options.summary_dir());
+                    summaries.restore_local(tcx, entry.summary);
+                    summaries.save_local(tcx);
+                    return;
+                }
             }
 
-            // alias analysis pass
-            let mut alias_anaysis_pass = AliasAnalysis::new(my_tcx);
-            alias_anaysis_pass.run_analysis();
-
-            if self.options.emit_alias_graph {
-                my_tcx.alias_graph.print_graph();
+            if self.options.emit_mir {
+                let mut show_mir = ShowMir::new(tcx);
+                show_mir.start();
             }
 
-            // lock set analysis pass
-            let mut lock_analysis_pass = LockSetAnalysis::new(my_tcx);
-            lock_analysis_pass.run_analysis();
-
-            if self.options.emit_lock_graph {
-                lock_analysis_pass.print_lock_set_facts();
-                lock_analysis_pass.lock_graph.print_loops();
+            let mut my_tcx = MyTcx::new(tcx, self.options.clone());
+            let strategy = self.options.strategy.clone();
+            self.run_strategy(&strategy, &mut my_tcx);
+            my_tcx.reporter.mark_incomplete(&my_tcx.incomplete);
+            my_tcx.report_skipped();
+
+            // export the lock summaries for the crates depending on this one
+            my_tcx.summaries.save_local(tcx);
+
+            match &cache_dir {
+                Some(dir) => cache::store(
+                    dir,
+                    tcx,
+                    &CacheEntry {
+                        summary: my_tcx.summaries.local_summary(tcx),
+                        findings: my_tcx.reporter.findings().to_vec(),
+                        notes: my_tcx.reporter.notes().to_vec(),
+                        ..CacheEntry::new(tcx, fingerprint)
+                    },
+                ),
+                // not launched by cargo, so nobody else prints the results
+                None => my_tcx.reporter.print(&krate),
             }
         });
         Compilation::Continue
@@ -174,7 +173,25 @@ impl rustc_driver::Callbacks for MyCallBacks {
 }
 
 #[derive(Default)]
-pub struct MyDriver;
+pub struct MyDriver {
+    checkers: Vec<Box<dyn BugChecker>>,
+    /// the binary run in place of rustc, `deadlock` if not set
+    driver_name: Option<Cow<'static, str>>,
+}
+
+impl MyDriver {
+    /// name the driver binary, for a custom driver built as a binary of its own
+    pub fn with_driver_name(mut self, name: impl Into<Cow<'static, str>>) -> Self {
+        self.driver_name = Some(name.into());
+        self
+    }
+
+    /// register a custom checker, run after the built-in ones of the selected strategy
+    pub fn with_checker(mut self, checker: impl BugChecker + 'static) -> Self {
+        self.checkers.push(Box::new(checker));
+        self
+    }
+}
 
 impl Plugin for MyDriver {
     type Args = Options;
@@ -184,22 +201,44 @@ impl Plugin for MyDriver {
     }
 
     fn driver_name(&self) -> Cow<'static, str> {
-        "deadlock".into()
+        self.driver_name.clone().unwrap_or("deadlock".into())
     }
 
     // In the CLI, we ask Clap to parse arguments and also specify a CrateFilter.
     // If one of the CLI arguments was a specific file to analyze, then you
     // could provide a different filter.
-    fn args(&self, _target_dir: &Utf8Path) -> RustcPluginArgs<Self::Args> {
-        let args = Options::parse_from(env::args().skip(1));
+    fn args(&self, target_dir: &Utf8Path) -> RustcPluginArgs<Self::Args> {
+        let mut args = Options::parse_from(env::args().skip(1));
+        if !STRATEGIES.contains(&args.strategy.as_str()) {
+            eprintln!(
+                "Unknown strategy {}, available strategies: {}",
+                args.strategy,
+                STRATEGIES.join(", ")
+            );
+            process::exit(1);
+        }
+        args.target_dir = Some(target_dir.to_string());
         println!("{:?}", args);
         let filter = CrateFilter::AllCrates;
         RustcPluginArgs { args, filter }
     }
 
-    // Pass Cargo arguments (like --feature) from the top-level CLI to Cargo.
+    // Cargo only re-runs the driver on crates that changed, so the results of
+    // the crates of this run are read back from the cache once Cargo is done.
+    fn after_cargo(&self, args: &Self::Args, _target_dir: &Utf8Path) {
+        if let Some(dir) = args.cache_dir() {
+            for (krate, reporter) in cache::run_reports(&dir, &process::id().to_string()) {
+                reporter.print(&krate);
+            }
+        }
+    }
+
+    // Pass Cargo arguments (like --feature) from the top-level CLI to Cargo,
+    // and name the run for the drivers to record their crates in; the name is left
+    // out of the plugin arguments, or cargo would rebuild every crate each run.
     fn modify_cargo(&self, cargo: &mut Command, args: &Self::Args) {
         cargo.args(&args.cargo_args);
+        cargo.env(cache::RUN_ENV, process::id().to_string());
     }
 
     // In the driver, we use the Rustc API to start a compiler session
@@ -210,7 +249,7 @@ impl Plugin for MyDriver {
         plugin_args: Self::Args,
     ) -> rustc_interface::interface::Result<()> {
         tracing::debug!("Rust Probe start to run.");
-        let mut callbacks = MyCallBacks::new(&plugin_args);
+        let mut callbacks = MyCallBacks::new(&plugin_args, self.checkers);
         let compiler = rustc_driver::RunCompiler::new(&compiler_args, &mut callbacks);
         compiler.run()
     }
@@ -1,8 +1,5 @@
 #![feature(rustc_private)]
 
-#[macro_use]
-extern crate lazy_static;
-
 extern crate rustc_ast_pretty;
 extern crate rustc_driver;
 extern crate rustc_error_codes;
@@ -19,9 +16,25 @@ extern crate rustc_target;
 extern crate rustc_type_ir;
 
 mod analysis;
+mod cache;
+mod checker;
 mod context;
 mod driver;
 mod option;
+mod pass;
+mod report;
 mod utils;
 
+pub use analysis::{
+    borrow::{BorrowOp, HeldBorrow},
+    callgraph::CallGraph,
+    condvar::{CondvarOp, CondvarOpKind},
+    lock::{Lock, LockFact, LockSetFact, LockSummary},
+    lockgraph::LockGraph,
+    raw::RawUnlock,
+    thread::ThreadSpawn,
+};
+pub use checker::{BugChecker, CheckerContext};
 pub use driver::MyDriver;
+pub use option::{AliasMode, Options};
+pub use report::{Finding, Reporter};
@@ -1,7 +1,34 @@
+use std::{fmt, path::PathBuf};
+
 use rustc_middle::ty::TyCtxt;
 use serde::{Deserialize, Serialize};
 
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+
+/// how assignments relate the alias nodes of their two sides
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AliasMode {
+    /// `a = b` merges the nodes of `a` and `b`, fast but merging every lock
+    /// that ever flows into the same variable
+    #[default]
+    Unification,
+    /// `a = b` makes `a` point to whatever `b` points to, keeping apart the locks
+    /// that only meet in a common variable
+    Inclusion,
+    /// the same as `inclusion`, but only the nodes the lock set analysis looks up
+    /// are solved, by queries walking back from them
+    Demand,
+}
+
+impl fmt::Display for AliasMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AliasMode::Unification => write!(f, "unification"),
+            AliasMode::Inclusion => write!(f, "inclusion"),
+            AliasMode::Demand => write!(f, "demand"),
+        }
+    }
+}
 
 #[derive(Parser, Clone, Debug, Serialize, Deserialize)]
 #[structopt(about = "This is a bug detector for Rust.")]
@@ -19,6 +46,44 @@ pub struct Options {
     #[arg(long = "emit-lock-graph")]
     pub emit_lock_graph: bool,
 
+    /// the analysis strategy to run, e.g. `double-lock` or `full`
+    #[arg(long = "strategy", default_value = "full")]
+    pub strategy: String,
+
+    /// an extra function that blocks, matched as a def path prefix,
+    /// e.g. `--blocking-fn my_crate::rpc::call`
+    #[arg(long = "blocking-fn")]
+    pub blocking_fns: Vec<String>,
+
+    /// analyze cleanup blocks too, and report locks poisoned by panics
+    /// in their critical sections or left locked by them
+    #[arg(long = "unwind-aware")]
+    pub unwind_aware: bool,
+
+    /// how many levels of callers get their own copies of a callee's alias nodes,
+    /// from 0 to 2; 0 binds every call site to the callee's own nodes, merging the
+    /// arguments of all its callers
+    #[arg(
+        long = "context-depth",
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u8).range(0..=2)
+    )]
+    pub context_depth: u8,
+
+    /// `unification` (the default), the more precise and slower `inclusion`, or
+    /// `demand`, as precise and only solving the locks and guards looked up
+    #[arg(long = "alias-mode", value_enum, default_value_t = AliasMode::Unification)]
+    pub alias_mode: AliasMode,
+
+    /// track the branch conditions locks are acquired under, and drop them on the
+    /// branches contradicting those, e.g. a second `if cond` dropping a guard
+    #[arg(long = "path-sensitive")]
+    pub path_sensitive: bool,
+
+    /// the plugin's target directory, filled in by the cargo frontend
+    #[arg(skip)]
+    pub target_dir: Option<String>,
+
     // FIXME: more compilation options
     #[structopt(last = true)]
     pub cargo_args: Vec<String>,
@@ -29,4 +94,21 @@ impl Options {
         tracing::info!("RustProbe runs under options: {:?}", self);
         //TODO: 在这里预处理一些选项，可能没用
     }
+
+    /// where the per-crate lock summaries are exchanged
+    pub fn summary_dir(&self) -> Option<PathBuf> {
+        self.target_dir
+            .as_ref()
+            .map(|dir| PathBuf::from(dir).join("deadlock"))
+    }
+
+    /// where the per-crate analysis results are cached
+    pub fn cache_dir(&self) -> Option<PathBuf> {
+        self.summary_dir().map(|dir| dir.join("cache"))
+    }
+
+    /// the graphs can only be emitted by a real analysis, not from the cache
+    pub fn emit_any(&self) -> bool {
+        self.emit_mir || self.emit_call_graph || self.emit_alias_graph || self.emit_lock_graph
+    }
 }
@@ -0,0 +1,295 @@
+//! Analysis passes and the strategies composing them.
+//!
+//! All passes of a strategy share one `MyTcx`. A pass declares the artifacts
+//! it requires and produces, and a strategy is only run if every requirement
+//! is produced by an earlier pass.
+
+use rustc_hash::FxHashSet;
+
+use crate::{
+    analysis::{
+        alias::AliasAnalysis, callgraph::CallGraphPass, prepass::run_prepass, LockSetAnalysis,
+    },
+    checker::{
+        await_lock::AwaitLockChecker,
+        blocking::BlockingChecker,
+        condvar::CondvarChecker,
+        join::JoinChecker,
+        leak::LeakChecker,
+        lock_order::{DoubleLockChecker, LockOrderChecker},
+        poison::PoisonChecker,
+        refcell::RefCellChecker,
+        unlock::UnlockChecker,
+        CheckerPass,
+    },
+    context::MyTcx,
+    option::Options,
+};
+
+/// data in `MyTcx` produced by a pass and consumed by later ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Artifact {
+    CallGraph,
+    AliasGraph,
+    /// lock set facts, lock graph, function summaries, spawned threads and cell borrows
+    LockSets,
+}
+
+pub trait AnalysisPass: Send {
+    fn name(&self) -> String;
+
+    fn requires(&self) -> Vec<Artifact> {
+        vec![]
+    }
+
+    fn produces(&self) -> Vec<Artifact> {
+        vec![]
+    }
+
+    fn before_run(&mut self) {
+        tracing::info!("{} analysis is running.", self.name());
+    }
+
+    fn run_pass<'tcx>(&mut self, my_tcx: &mut MyTcx<'tcx>);
+
+    fn after_run<'tcx>(&mut self, _my_tcx: &mut MyTcx<'tcx>) {}
+}
+
+/// a strategy consists of all necessary passes
+pub struct Strategy {
+    pub name: String,
+    passes: Vec<Box<dyn AnalysisPass>>,
+}
+
+impl Strategy {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: String::from(name),
+            passes: Vec::new(),
+        }
+    }
+
+    /// register pass in a strategy
+    pub fn register_pass(&mut self, pass: Box<dyn AnalysisPass>) {
+        self.passes.push(pass);
+    }
+
+    pub fn pass_names(&self) -> Vec<String> {
+        self.passes.iter().map(|pass| pass.name()).collect()
+    }
+
+    /// every artifact a pass requires must be produced by an earlier pass
+    pub fn validate(&self) -> Result<(), String> {
+        let mut produced = FxHashSet::default();
+        for pass in &self.passes {
+            for artifact in pass.requires() {
+                if !produced.contains(&artifact) {
+                    return Err(format!(
+                        "pass {} of strategy {} requires {:?}, which no earlier pass produces",
+                        pass.name(),
+                        self.name,
+                        artifact
+                    ));
+                }
+            }
+            produced.extend(pass.produces());
+        }
+        Ok(())
+    }
+
+    pub fn run<'tcx>(&mut self, my_tcx: &mut MyTcx<'tcx>) -> Result<(), String> {
+        self.validate()?;
+        for pass in &mut self.passes {
+            pass.before_run();
+            pass.run_pass(my_tcx);
+            pass.after_run(my_tcx);
+        }
+        Ok(())
+    }
+}
+
+/// names accepted by `--strategy`
+pub const STRATEGIES: [&str; 2] = ["double-lock", "full"];
+
+pub fn builtin_strategies(options: &Options) -> Vec<Strategy> {
+    let mut double_lock = Strategy::new("double-lock");
+    double_lock.register_pass(Box::new(CallGraphConstruction));
+    double_lock.register_pass(Box::new(PrepassPass));
+    double_lock.register_pass(Box::new(AliasAnalysisPass));
+    double_lock.register_pass(Box::new(LockSetAnalysisPass));
+    double_lock.register_pass(Box::new(CheckerPass::new(Box::new(DoubleLockChecker))));
+    double_lock.register_pass(Box::new(CheckerPass::new(Box::new(JoinChecker))));
+
+    let mut full = Strategy::new("full");
+    full.register_pass(Box::new(CallGraphConstruction));
+    full.register_pass(Box::new(PrepassPass));
+    full.register_pass(Box::new(AliasAnalysisPass));
+    full.register_pass(Box::new(LockSetAnalysisPass));
+    full.register_pass(Box::new(CheckerPass::new(Box::new(DoubleLockChecker))));
+    full.register_pass(Box::new(CheckerPass::new(Box::new(JoinChecker))));
+    full.register_pass(Box::new(CheckerPass::new(Box::new(LockOrderChecker))));
+    full.register_pass(Box::new(CheckerPass::new(Box::new(CondvarChecker))));
+    full.register_pass(Box::new(CheckerPass::new(Box::new(AwaitLockChecker))));
+    full.register_pass(Box::new(CheckerPass::new(Box::new(RefCellChecker))));
+    full.register_pass(Box::new(CheckerPass::new(Box::new(UnlockChecker))));
+    // both look at what happens while unwinding, which only the unwind-aware mode analyzes
+    if options.unwind_aware {
+        full.register_pass(Box::new(CheckerPass::new(Box::new(PoisonChecker))));
+        full.register_pass(Box::new(CheckerPass::new(Box::new(LeakChecker))));
+    }
+    full.register_pass(Box::new(CheckerPass::new(Box::new(BlockingChecker::new(
+        &options.blocking_fns,
+    )))));
+
+    vec![double_lock, full]
+}
+
+pub struct CallGraphConstruction;
+
+impl AnalysisPass for CallGraphConstruction {
+    fn name(&self) -> String {
+        "[Call Graph pre build]".to_string()
+    }
+
+    fn produces(&self) -> Vec<Artifact> {
+        vec![Artifact::CallGraph]
+    }
+
+    fn run_pass<'tcx>(&mut self, my_tcx: &mut MyTcx<'tcx>) {
+        let emit_call_graph = my_tcx.options.emit_call_graph;
+        let mut call_graph_pass = CallGraphPass::new(my_tcx);
+        call_graph_pass.start();
+        if emit_call_graph {
+            call_graph_pass.print_topo();
+        }
+    }
+}
+
+/// the per-function facts of the later passes, computed in parallel when rustc can
+pub struct PrepassPass;
+
+impl AnalysisPass for PrepassPass {
+    fn name(&self) -> String {
+        "[Prepass]".to_string()
+    }
+
+    fn requires(&self) -> Vec<Artifact> {
+        vec![Artifact::CallGraph]
+    }
+
+    fn run_pass<'tcx>(&mut self, my_tcx: &mut MyTcx<'tcx>) {
+        my_tcx.prepass = run_prepass(my_tcx.tcx, &my_tcx.call_graph, &my_tcx.options);
+    }
+}
+
+pub struct AliasAnalysisPass;
+
+impl AnalysisPass for AliasAnalysisPass {
+    fn name(&self) -> String {
+        "[Alias Analysis]".to_string()
+    }
+
+    fn requires(&self) -> Vec<Artifact> {
+        vec![Artifact::CallGraph]
+    }
+
+    fn produces(&self) -> Vec<Artifact> {
+        vec![Artifact::AliasGraph]
+    }
+
+    fn run_pass<'tcx>(&mut self, my_tcx: &mut MyTcx<'tcx>) {
+        AliasAnalysis::new(my_tcx).run_analysis();
+    }
+
+    fn after_run<'tcx>(&mut self, my_tcx: &mut MyTcx<'tcx>) {
+        if my_tcx.options.emit_alias_graph {
+            my_tcx.alias_graph.print_graph();
+        }
+    }
+}
+
+pub struct LockSetAnalysisPass;
+
+impl AnalysisPass for LockSetAnalysisPass {
+    fn name(&self) -> String {
+        "[Lock Set Analysis]".to_string()
+    }
+
+    fn requires(&self) -> Vec<Artifact> {
+        vec![Artifact::CallGraph, Artifact::AliasGraph]
+    }
+
+    fn produces(&self) -> Vec<Artifact> {
+        vec![Artifact::LockSets]
+    }
+
+    fn run_pass<'tcx>(&mut self, my_tcx: &mut MyTcx<'tcx>) {
+        LockSetAnalysis::new(my_tcx).run_analysis();
+    }
+
+    fn after_run<'tcx>(&mut self, my_tcx: &mut MyTcx<'tcx>) {
+        if my_tcx.options.emit_lock_graph {
+            my_tcx.print_lock_set_facts();
+            my_tcx.lock_graph.print_loops();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    struct Dummy {
+        requires: Vec<Artifact>,
+        produces: Vec<Artifact>,
+    }
+
+    impl AnalysisPass for Dummy {
+        fn name(&self) -> String {
+            "dummy".to_string()
+        }
+
+        fn requires(&self) -> Vec<Artifact> {
+            self.requires.clone()
+        }
+
+        fn produces(&self) -> Vec<Artifact> {
+            self.produces.clone()
+        }
+
+        fn run_pass<'tcx>(&mut self, _my_tcx: &mut MyTcx<'tcx>) {}
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut strategy = Strategy::new("test");
+        strategy.register_pass(Box::new(Dummy {
+            requires: vec![],
+            produces: vec![Artifact::CallGraph],
+        }));
+        strategy.register_pass(Box::new(Dummy {
+            requires: vec![Artifact::CallGraph],
+            produces: vec![Artifact::AliasGraph],
+        }));
+        assert!(strategy.validate().is_ok());
+
+        strategy.register_pass(Box::new(Dummy {
+            requires: vec![Artifact::LockSets],
+            produces: vec![],
+        }));
+        assert!(strategy.validate().is_err());
+    }
+
+    #[test]
+    fn test_builtin_strategies() {
+        let options = Options::parse_from(["deadlock"]);
+        let strategies = builtin_strategies(&options);
+        assert_eq!(strategies.len(), STRATEGIES.len());
+        for strategy in strategies {
+            assert!(STRATEGIES.contains(&strategy.name.as_str()));
+            assert!(strategy.validate().is_ok());
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! Bugs found by the analyses.
+
+use std::fmt::Write;
+
+use rustc_hash::FxHashSet;
+use rustc_hir::def_id::DefId;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Finding {
+    /// the checker that found the bug
+    pub checker: String,
+    pub message: String,
+    /// related program locations and locks, in a readable form
+    pub trace: Vec<String>,
+    /// the functions whose facts the finding comes from
+    #[serde(skip)]
+    pub functions: Vec<DefId>,
+    /// one of them calls a function whose analysis failed, so locks may be missing
+    #[serde(default)]
+    pub incomplete: bool,
+}
+
+impl Finding {
+    pub fn new(checker: &str, message: String) -> Self {
+        Self {
+            checker: String::from(checker),
+            message,
+            trace: Vec::new(),
+            functions: Vec::new(),
+            incomplete: false,
+        }
+    }
+
+    pub fn within(mut self, def_id: DefId) -> Self {
+        if !self.functions.contains(&def_id) {
+            self.functions.push(def_id);
+        }
+        self
+    }
+
+    pub fn with_trace(mut self, item: String) -> Self {
+        self.trace.push(item);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Reporter {
+    findings: Vec<Finding>,
+    /// facts about the analysis itself, printed after the findings
+    #[serde(default)]
+    notes: Vec<String>,
+}
+
+impl Reporter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn report(&mut self, finding: Finding) {
+        if !self.findings.contains(&finding) {
+            self.findings.push(finding);
+        }
+    }
+
+    /// mark the findings coming from a function that crosses a failed analysis
+    pub fn mark_incomplete(&mut self, incomplete: &FxHashSet<DefId>) {
+        for finding in self.findings.iter_mut() {
+            if finding.functions.iter().any(|def_id| incomplete.contains(def_id)) {
+                finding.incomplete = true;
+            }
+        }
+    }
+
+    pub fn findings(&self) -> &[Finding] {
+        &self.findings
+    }
+
+    pub fn note(&mut self, note: String) {
+        self.notes.push(note);
+    }
+
+    pub fn notes(&self) -> &[String] {
+        &self.notes
+    }
+
+    pub fn with_notes(mut self, notes: Vec<String>) -> Self {
+        self.notes.extend(notes);
+        self
+    }
+
+    pub fn print(&self, krate: &str) {
+        print!("{}", self.render(krate));
+    }
+
+    /// the findings and notes of a crate, as they are printed
+    pub fn render(&self, krate: &str) -> String {
+        let mut out = String::new();
+        if self.findings.is_empty() {
+            let _ = writeln!(out, "No bug found in crate {}.", krate);
+        } else {
+            let _ = writeln!(out, "{} bug(s) found in crate {}:", self.findings.len(), krate);
+        }
+        for finding in &self.findings {
+            let _ = writeln!(out, "[{}] {}", finding.checker, finding.message);
+            for item in &finding.trace {
+                let _ = writeln!(out, "    {}", item);
+            }
+            if finding.incomplete {
+                let _ = writeln!(
+                    out,
+                    "    (may be incomplete: a call on the way reaches a function whose \
+                     analysis failed)"
+                );
+            }
+        }
+        for note in &self.notes {
+            let _ = writeln!(out, "note: {}", note);
+        }
+        out
+    }
+}
+
+impl From<Vec<Finding>> for Reporter {
+    fn from(findings: Vec<Finding>) -> Self {
+        Self {
+            findings,
+            notes: Vec::new(),
+        }
+    }
+}
@@ -49,7 +49,7 @@ impl<'tcx> Display for TerminatorKind<'tcx> {
                 },
                 _ => (),
             },
-            TerminatorKind::CoroutineDrop => todo!(),
+            TerminatorKind::CoroutineDrop => s += "CoroutineDrop",
         };
         s
     }
@@ -0,0 +1,102 @@
+#![feature(rustc_private)]
+
+extern crate rustc_middle;
+
+use std::{
+    env, fs,
+    sync::{Arc, Mutex},
+};
+
+use clap::Parser;
+use rust_deadlock::{BugChecker, CheckerContext, Finding, MyDriver, Options, Reporter};
+use rustc_compat::Plugin;
+use rustc_middle::mir::{Local, Place};
+
+const SOURCE: &str = r#"
+use std::sync::Mutex;
+
+pub fn helper(data: &Mutex<i32>) -> i32 {
+    *data.lock().unwrap()
+}
+
+pub fn twice(data: &Mutex<i32>) -> i32 {
+    let guard = data.lock().unwrap();
+    *guard + helper(data)
+}
+"#;
+
+/// reports every call made while a lock is held, and records what it saw
+struct CallUnderLock {
+    seen: Arc<Mutex<Vec<String>>>,
+}
+
+impl BugChecker for CallUnderLock {
+    fn name(&self) -> String {
+        "Call Under Lock".to_string()
+    }
+
+    fn check<'tcx>(&mut self, cx: &CheckerContext<'_, 'tcx>, reporter: &mut Reporter) {
+        let tcx = cx.tcx();
+        let mut seen = self.seen.lock().unwrap();
+        for def_id in cx.functions() {
+            let name = tcx.def_path_str(def_id);
+            // the first parameter names itself
+            let param = Place::from(Local::from_usize(1));
+            if cx.may_alias(def_id, &param, &param) {
+                seen.push(format!("{} has a parameter", name));
+            }
+            for bb in cx.body(def_id).basic_blocks.indices() {
+                let location = cx.body(def_id).terminator_loc(bb);
+                let Some(callee) = cx.callee_at(def_id, location) else {
+                    continue;
+                };
+                let callee = tcx.def_path_str(callee);
+                if !callee.ends_with("helper") || cx.held_locks(def_id, location).is_empty() {
+                    continue;
+                }
+                seen.push(format!("{} calls {} under a lock", name, callee));
+                reporter.report(
+                    Finding::new("call-under-lock", format!("{} calls {}", name, callee))
+                        .within(def_id)
+                        .with_trace(cx.describe_location(def_id, location)),
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_custom_checker() {
+    let dir = env::temp_dir().join(format!("deadlock-custom-checker-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let source = dir.join("lib.rs");
+    fs::write(&source, SOURCE).unwrap();
+
+    let seen = Arc::new(Mutex::new(vec![]));
+    let driver = MyDriver::default()
+        .with_driver_name("custom-deadlock")
+        .with_checker(CallUnderLock { seen: seen.clone() });
+    assert_eq!(driver.driver_name(), "custom-deadlock");
+
+    let compiler_args = [
+        "rustc",
+        source.to_str().unwrap(),
+        "--crate-type",
+        "lib",
+        "--edition",
+        "2021",
+        "--out-dir",
+        dir.to_str().unwrap(),
+        "-A",
+        "warnings",
+    ];
+    let options = Options::parse_from(["deadlock"]);
+    let result = driver.run(compiler_args.iter().map(|arg| arg.to_string()).collect(), options);
+    fs::remove_dir_all(&dir).unwrap();
+    assert!(result.is_ok());
+
+    let seen = seen.lock().unwrap();
+    assert!(seen.contains(&"helper has a parameter".to_string()));
+    assert!(seen.contains(&"twice calls helper under a lock".to_string()));
+    assert!(!seen.iter().any(|line| line.starts_with("helper calls")));
+}
@@ -0,0 +1,32 @@
+// one lock-order cycle between two tokio tasks, each holding one async mutex
+// across the `.lock().await` of the other, and one double lock of a futures
+// mutex held across an await, under the default unification alias mode
+
+use std::sync::Arc;
+
+use tokio::sync::Mutex;
+
+async fn transfer(from: Arc<Mutex<i32>>, to: Arc<Mutex<i32>>) {
+    let mut from = from.lock().await;
+    let mut to = to.lock().await;
+    *from -= 1;
+    *to += 1;
+}
+
+async fn relock(counter: &futures::lock::Mutex<i32>) {
+    let first = counter.lock().await;
+    let second = counter.lock().await;
+    println!("{} {}", *first, *second);
+}
+
+#[tokio::main]
+async fn main() {
+    let a = Arc::new(Mutex::new(0));
+    let b = Arc::new(Mutex::new(0));
+    let one = tokio::spawn(transfer(Arc::clone(&a), Arc::clone(&b)));
+    let other = tokio::spawn(transfer(b, a));
+    let _ = one.await;
+    let _ = other.await;
+
+    relock(&futures::lock::Mutex::new(0)).await;
+}
@@ -0,0 +1,43 @@
+// two await-lock findings: a std guard returned from a helper and a std guard
+// kept in a struct, both alive across an await; `flush` drops its guard before
+// awaiting and is fine
+
+use std::sync::{Mutex, MutexGuard};
+
+struct Batch<'a> {
+    items: MutexGuard<'a, Vec<u32>>,
+}
+
+fn queue(items: &Mutex<Vec<u32>>) -> MutexGuard<'_, Vec<u32>> {
+    items.lock().unwrap()
+}
+
+async fn send(n: u32) -> u32 {
+    n + 1
+}
+
+async fn enqueue(items: &Mutex<Vec<u32>>) {
+    let mut queue = queue(items);
+    let n = send(1).await;
+    queue.push(n);
+}
+
+async fn batch(items: &Mutex<Vec<u32>>) {
+    let mut batch = Batch {
+        items: items.lock().unwrap(),
+    };
+    let n = send(2).await;
+    batch.items.push(n);
+}
+
+async fn flush(items: &Mutex<Vec<u32>>) {
+    let len = items.lock().unwrap().len() as u32;
+    send(len).await;
+}
+
+fn main() {
+    let items = Mutex::new(vec![]);
+    drop(enqueue(&items));
+    drop(batch(&items));
+    drop(flush(&items));
+}
@@ -0,0 +1,39 @@
+// one lock/channel cycle, in `stuck`: it waits in `recv()` while it holds
+// `state`, which the worker needs before it can `send()`; the worker of
+// `report` sends without the lock, so no cycle there, though the blocking
+// checker still reports both `recv()` calls made under a lock
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+fn stuck(state: Arc<Mutex<i32>>) {
+    let (tx, rx) = mpsc::channel();
+    let shared = Arc::clone(&state);
+    let worker = thread::spawn(move || {
+        let mut value = shared.lock().unwrap();
+        *value += 1;
+        tx.send(*value).unwrap();
+    });
+    let value = state.lock().unwrap();
+    let reply = rx.recv().unwrap();
+    println!("{} {}", *value, reply);
+    drop(value);
+    worker.join().unwrap();
+}
+
+fn report(state: Arc<Mutex<i32>>) {
+    let (tx, rx) = mpsc::channel();
+    let worker = thread::spawn(move || {
+        tx.send(1).unwrap();
+    });
+    let value = state.lock().unwrap();
+    let reply = rx.recv().unwrap();
+    println!("{} {}", *value, reply);
+    drop(value);
+    worker.join().unwrap();
+}
+
+fn main() {
+    stuck(Arc::new(Mutex::new(0)));
+    report(Arc::new(Mutex::new(0)));
+}
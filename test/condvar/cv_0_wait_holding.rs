@@ -0,0 +1,40 @@
+// one condvar bug, in `consume`: it waits on `ready` with `queue`'s guard while
+// it still holds `stats`, which `produce` needs before it can notify, so the
+// consumer sleeps holding a lock its waker is blocked on
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+struct Shared {
+    queue: Mutex<Vec<i32>>,
+    ready: Condvar,
+    stats: Mutex<i32>,
+}
+
+fn produce(shared: &Shared) {
+    let mut stats = shared.stats.lock().unwrap();
+    *stats += 1;
+    shared.queue.lock().unwrap().push(*stats);
+    shared.ready.notify_one();
+}
+
+fn consume(shared: &Shared) -> i32 {
+    let mut stats = shared.stats.lock().unwrap();
+    let mut queue = shared.queue.lock().unwrap();
+    while queue.is_empty() {
+        queue = shared.ready.wait(queue).unwrap();
+    }
+    *stats -= 1;
+    queue.pop().unwrap()
+}
+
+fn main() {
+    let shared = Arc::new(Shared {
+        queue: Mutex::new(Vec::new()),
+        ready: Condvar::new(),
+        stats: Mutex::new(0),
+    });
+    let producer = Arc::clone(&shared);
+    let worker = thread::spawn(move || produce(&producer));
+    println!("{}", consume(&shared));
+    worker.join().unwrap();
+}
@@ -0,0 +1,23 @@
+// no bug: the consumer holds only the mutex paired with the condvar while it
+// waits, and re-checks the predicate in a loop, so spurious wakeups are handled;
+// the producer notifies after it released the mutex
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+fn main() {
+    let pair = Arc::new((Mutex::new(false), Condvar::new()));
+    let producer = Arc::clone(&pair);
+    let worker = thread::spawn(move || {
+        let (ready, signal) = &*producer;
+        *ready.lock().unwrap() = true;
+        signal.notify_one();
+    });
+
+    let (ready, signal) = &*pair;
+    let mut done = ready.lock().unwrap();
+    while !*done {
+        done = signal.wait(done).unwrap();
+    }
+    drop(done);
+    worker.join().unwrap();
+}
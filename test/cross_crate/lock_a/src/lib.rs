@@ -0,0 +1,16 @@
+// the lock-taking functions `lock_b` calls; their lock summaries are all
+// `lock_b` knows of them
+use std::sync::Mutex;
+
+/// locks `first` and then `second`, holding both
+pub fn transfer(first: &Mutex<i32>, second: &Mutex<i32>) {
+    let mut from = first.lock().unwrap();
+    let mut to = second.lock().unwrap();
+    *to += *from;
+    *from = 0;
+}
+
+/// locks `counter` for the time of an increment
+pub fn bump(counter: &Mutex<i32>) {
+    *counter.lock().unwrap() += 1;
+}
@@ -0,0 +1,22 @@
+// two bugs found through `lock_a`'s summaries: `audit` locks `savings` and
+// then `checking`, while `lock_a::transfer` locks them the other way round,
+// and `main` calls `lock_a::bump` on the counter it holds
+use std::sync::Mutex;
+
+fn audit(checking: &Mutex<i32>, savings: &Mutex<i32>) -> i32 {
+    let savings = savings.lock().unwrap();
+    let checking = checking.lock().unwrap();
+    *savings + *checking
+}
+
+fn main() {
+    let checking = Mutex::new(10);
+    let savings = Mutex::new(20);
+    lock_a::transfer(&checking, &savings);
+    println!("{}", audit(&checking, &savings));
+
+    let counter = Mutex::new(0);
+    let held = counter.lock().unwrap();
+    lock_a::bump(&counter);
+    println!("{}", *held);
+}
@@ -0,0 +1,24 @@
+// no bug: `a` and `b` are locked one after the other, and then the one `pick`
+// returns alone. The unification mode merges what `pick` returns with both its
+// parameters, so `a` and `b` become one lock node and taking `b` while holding `a`
+// is a double lock, while the inclusion and the demand-driven modes keep them apart
+use std::sync::Mutex;
+
+fn pick<'a>(first: bool, a: &'a Mutex<i32>, b: &'a Mutex<i32>) -> &'a Mutex<i32> {
+    if first {
+        a
+    } else {
+        b
+    }
+}
+
+fn main() {
+    let a = Mutex::new(1);
+    let b = Mutex::new(2);
+    let ga = a.lock().unwrap();
+    let gb = b.lock().unwrap();
+    println!("{} {}", *ga, *gb);
+    drop(gb);
+    drop(ga);
+    *pick(std::env::args().count() > 1, &a, &b).lock().unwrap() += 1;
+}
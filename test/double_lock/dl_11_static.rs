@@ -0,0 +1,31 @@
+// two bugs, as every use of a static names the same lock: `a` locks `M` and then
+// `N`, while `b` has `both` lock them the other way round through references to
+// the statics, and `main` locks `M` while holding it
+use std::sync::Mutex;
+
+static M: Mutex<i32> = Mutex::new(1);
+static N: Mutex<i32> = Mutex::new(2);
+
+fn both(first: &Mutex<i32>, second: &Mutex<i32>) {
+    let first = first.lock().unwrap();
+    let second = second.lock().unwrap();
+    println!("{} {}", *first, *second);
+}
+
+fn a() {
+    let m = M.lock().unwrap();
+    let n = N.lock().unwrap();
+    println!("{} {}", *m, *n);
+}
+
+fn b() {
+    both(&N, &M);
+}
+
+fn main() {
+    a();
+    b();
+    let first = M.lock().unwrap();
+    let second = M.lock().unwrap();
+    println!("{} {}", *first, *second);
+}
@@ -0,0 +1,18 @@
+// double lock on one branch: the lock is held after the `if` if it is held
+// at the end of either branch
+
+use std::sync::Mutex;
+
+fn main() {
+    let lock = Mutex::new(0);
+    let verbose = std::env::args().count() > 1;
+    let first = if verbose {
+        Some(lock.lock().unwrap())
+    } else {
+        println!("quiet");
+        None
+    };
+    let mut num = lock.lock().unwrap();
+    *num += 1;
+    drop(first);
+}
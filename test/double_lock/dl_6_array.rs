@@ -0,0 +1,19 @@
+// no double lock: each element of a lock array is a lock of its own, while two
+// elements at indices the analysis cannot tell may or may not be the same lock
+use std::sync::Mutex;
+
+fn neighbours(forks: &[Mutex<i32>], i: usize) {
+    let left = forks[i].lock().unwrap();
+    let right = forks[(i + 1) % forks.len()].lock().unwrap();
+    drop(right);
+    drop(left);
+}
+
+fn main() {
+    let forks = [Mutex::new(0), Mutex::new(1)];
+    let a = forks[0].lock().unwrap();
+    let b = forks[1].lock().unwrap();
+    drop(b);
+    drop(a);
+    neighbours(&forks, 0);
+}
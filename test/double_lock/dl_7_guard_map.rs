@@ -0,0 +1,21 @@
+// one double lock, on `held`: a guard mapped to a field keeps its mutex held
+// until the mapped guard is dropped, so `dropped` can be locked again after it
+#![feature(mapped_lock_guards)]
+use std::sync::{Mutex, MutexGuard};
+
+struct Pair {
+    left: i32,
+    right: i32,
+}
+
+fn main() {
+    let dropped = Mutex::new(Pair { left: 0, right: 1 });
+    let left = MutexGuard::map(dropped.lock().unwrap(), |pair| &mut pair.left);
+    drop(left);
+    let right = dropped.lock().unwrap();
+    drop(right);
+
+    let held = Mutex::new(Pair { left: 0, right: 1 });
+    let mut left = MutexGuard::map(held.lock().unwrap(), |pair| &mut pair.left);
+    *left += held.lock().unwrap().right;
+}
@@ -0,0 +1,34 @@
+// two double locks: `queue` returns its guard, so `items` is still held when
+// `main` locks it again, and `Batch` owns a guard, which holds `pending` until
+// the batch is dropped; `done` is locked again only after its batch is dropped
+use std::sync::{Mutex, MutexGuard};
+
+fn queue(items: &Mutex<i32>) -> MutexGuard<'_, i32> {
+    items.lock().unwrap()
+}
+
+struct Batch<'a> {
+    items: MutexGuard<'a, i32>,
+}
+
+fn batch(items: &Mutex<i32>) -> Batch<'_> {
+    Batch {
+        items: items.lock().unwrap(),
+    }
+}
+
+fn main() {
+    let items = Mutex::new(1);
+    let mut queued = queue(&items);
+    *queued += *items.lock().unwrap();
+
+    let pending = Mutex::new(2);
+    let mut open = batch(&pending);
+    *open.items += *pending.lock().unwrap();
+
+    let done = Mutex::new(3);
+    let closed = batch(&done);
+    let count = *closed.items;
+    drop(closed);
+    *done.lock().unwrap() += count;
+}
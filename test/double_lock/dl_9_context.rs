@@ -0,0 +1,17 @@
+// no bug at the default context depth of 1: `with` is called on `a` and on `b`,
+// and each call site gets its own copy of the helper's nodes, so `b` is not `a`
+// when it is locked while `a` is held. At `--context-depth 0` both calls bind the
+// same parameter, which the unification mode merges the two mutexes through
+use std::sync::Mutex;
+
+fn with(m: &Mutex<i32>) -> i32 {
+    *m.lock().unwrap()
+}
+
+fn main() {
+    let a = Mutex::new(1);
+    let b = Mutex::new(2);
+    with(&a);
+    let _g = a.lock().unwrap();
+    with(&b);
+}
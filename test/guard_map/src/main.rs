@@ -0,0 +1,39 @@
+// two double locks, on `held` and on `shared`: a guard mapped with `try_map` or
+// taken with `lock_arc` keeps its mutex held until it is dropped, so `dropped`
+// and `released` can be locked again after it
+
+use std::sync::Arc;
+
+use parking_lot::{Mutex, MutexGuard};
+
+struct Pair {
+    left: Option<i32>,
+    right: i32,
+}
+
+fn pair() -> Pair {
+    Pair { left: Some(0), right: 1 }
+}
+
+fn main() {
+    let dropped = Mutex::new(pair());
+    let Ok(left) = MutexGuard::try_map(dropped.lock(), |pair| pair.left.as_mut()) else {
+        return;
+    };
+    drop(left);
+    dropped.lock().right += 1;
+
+    let held = Mutex::new(pair());
+    if let Ok(mut left) = MutexGuard::try_map(held.lock(), |pair| pair.left.as_mut()) {
+        *left += held.lock().right;
+    }
+
+    let released = Arc::new(Mutex::new(pair()));
+    let guard = released.lock_arc();
+    drop(guard);
+    released.lock().right += 1;
+
+    let shared = Arc::new(Mutex::new(pair()));
+    let guard = shared.lock_arc();
+    shared.lock().right += guard.right;
+}
@@ -0,0 +1,14 @@
+// no double lock with --path-sensitive: the guard taken when `cond` holds is
+// dropped when it holds again, so `m` is free on both paths before the last lock;
+// without it, the branches are merged and a double lock is reported
+use std::sync::Mutex;
+
+fn main() {
+    let m = Mutex::new(0);
+    let cond = std::env::args().count() > 1;
+    let g = if cond { Some(m.lock().unwrap()) } else { None };
+    if cond {
+        drop(g);
+    }
+    let _h = m.lock().unwrap();
+}
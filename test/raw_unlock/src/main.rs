@@ -0,0 +1,31 @@
+// one double unlock in `release_twice` and one unmatched unlock in `swapped`;
+// `release` unlocks a lock it never acquires, which is left to its caller
+
+use lock_api::RawMutex as _;
+use parking_lot::Mutex;
+
+fn release_twice(m: &Mutex<i32>) {
+    unsafe { m.raw().lock() };
+    unsafe { m.raw().unlock() };
+    unsafe { m.raw().unlock() };
+}
+
+fn swapped(m: &Mutex<i32>) {
+    unsafe { m.raw().unlock() };
+    unsafe { m.raw().lock() };
+}
+
+fn release(m: &Mutex<i32>) {
+    unsafe { m.raw().unlock() };
+}
+
+fn main() {
+    let m = Mutex::new(0);
+    release_twice(&m);
+    swapped(&m);
+    release(&m);
+
+    let n = Mutex::new(0);
+    unsafe { n.raw().lock() };
+    release(&n);
+}
@@ -0,0 +1,10 @@
+// one conflict in `main`: `items` is borrowed mutably while the `Ref` taken
+// for `len` is still alive, which panics with `BorrowMutError`
+use std::cell::RefCell;
+
+fn main() {
+    let items = RefCell::new(vec![1, 2]);
+    let len = items.borrow();
+    items.borrow_mut().push(3);
+    println!("{}", len.len());
+}
@@ -0,0 +1,15 @@
+// one conflict: `main` holds a `RefMut` of `log` when it calls `record`, whose
+// `borrow` of the same cell panics with `BorrowError`
+use std::cell::RefCell;
+
+fn record(log: &RefCell<Vec<String>>) -> usize {
+    log.borrow().len()
+}
+
+fn main() {
+    let log = RefCell::new(Vec::new());
+    let mut entries = log.borrow_mut();
+    entries.push(String::from("start"));
+    println!("{}", record(&log));
+    entries.push(String::from("end"));
+}
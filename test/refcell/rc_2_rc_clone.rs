@@ -0,0 +1,13 @@
+// one conflict: `shared` and `other` are clones of one `Rc`, so the `RefMut`
+// taken through `other` conflicts with the one `shared` still holds
+use std::cell::RefCell;
+use std::rc::Rc;
+
+fn main() {
+    let shared = Rc::new(RefCell::new(0));
+    let other = Rc::clone(&shared);
+    let mut first = shared.borrow_mut();
+    *first += 1;
+    *other.borrow_mut() += 1;
+    println!("{}", *first);
+}
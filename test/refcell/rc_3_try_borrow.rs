@@ -0,0 +1,14 @@
+// no bug: `try_borrow_mut` returns an error instead of panicking while the
+// `Ref` is alive, and the later `borrow_mut` comes after the `Ref` is dropped
+use std::cell::RefCell;
+
+fn main() {
+    let cell = RefCell::new(1);
+    let read = cell.borrow();
+    if let Ok(mut write) = cell.try_borrow_mut() {
+        *write += 1;
+    }
+    println!("{}", *read);
+    drop(read);
+    *cell.borrow_mut() += 1;
+}
@@ -0,0 +1,40 @@
+// no lock-order cycle: `withdraw` takes `to` after `from`, while `deposit` only
+// tries `from` after `to` and backs off when it is taken; one double lock in
+// `main`, whose `try_lock` holds the lock on its success branch only
+
+use std::sync::Mutex;
+use std::thread;
+
+fn withdraw(from: &Mutex<i32>, to: &Mutex<i32>) {
+    let mut from = from.lock().unwrap();
+    let mut to = to.lock().unwrap();
+    *from -= 1;
+    *to += 1;
+}
+
+fn deposit(from: &Mutex<i32>, to: &Mutex<i32>) {
+    loop {
+        let mut to = to.lock().unwrap();
+        if let Ok(mut from) = from.try_lock() {
+            *from -= 1;
+            *to += 1;
+            return;
+        }
+        drop(to);
+        thread::yield_now();
+    }
+}
+
+fn main() {
+    let a = Mutex::new(10);
+    let b = Mutex::new(10);
+    thread::scope(|s| {
+        s.spawn(|| withdraw(&a, &b));
+        deposit(&a, &b);
+    });
+    let total = match a.try_lock() {
+        Ok(balance) => *balance + *a.lock().unwrap(),
+        Err(_) => *a.lock().unwrap(),
+    };
+    println!("{}", total);
+}
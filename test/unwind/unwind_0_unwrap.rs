@@ -0,0 +1,11 @@
+// no unwind leak with --unwind-aware: if `unwrap()` panics, it drops the guard
+// in the `Err` it was given, and a panic after it drops the guard in cleanup
+
+use std::sync::Mutex;
+
+fn main() {
+    let lock = Mutex::new(0);
+    let mut num = lock.lock().unwrap();
+    *num += 1;
+    println!("value: {}", *num);
+}
@@ -0,0 +1,15 @@
+// no unwind leak with --unwind-aware: the spawned thread's guard is dropped
+// on every unwinding path of the closure
+
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+fn main() {
+    let lock = Arc::new(Mutex::new(0));
+    let lock_clone = Arc::clone(&lock);
+    let handle = thread::spawn(move || {
+        let mut num = lock_clone.lock().unwrap();
+        *num += 1;
+    });
+    handle.join().unwrap();
+}
@@ -0,0 +1,26 @@
+// one lock poisoning with --unwind-aware: a worker panicking on a bad job while it
+// holds the queue poisons it, and every other worker of the pool then panics on
+// its own `.lock().unwrap()`, one after the other
+
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+fn main() {
+    let queue = Arc::new(Mutex::new(vec!["1", "2", "x"]));
+    let mut workers = vec![];
+    for _ in 0..4 {
+        let queue = Arc::clone(&queue);
+        workers.push(thread::spawn(move || loop {
+            let mut jobs = queue.lock().unwrap();
+            let Some(job) = jobs.pop() else {
+                break;
+            };
+            let n: u32 = job.parse().unwrap();
+            drop(jobs);
+            println!("{}", n);
+        }));
+    }
+    for worker in workers {
+        let _ = worker.join();
+    }
+}
@@ -0,0 +1,14 @@
+// no unwind leak with --unwind-aware: the guard is dropped by hand on one path,
+// and a drop flag tells the unwinding paths whether it is still to be dropped
+
+use std::sync::Mutex;
+
+fn main() {
+    let jobs = Mutex::new(vec!["1", "2"]);
+    let guard = jobs.lock().unwrap();
+    if guard.len() > 1 {
+        drop(guard);
+    }
+    let n: u32 = std::env::args().nth(1).unwrap_or_default().parse().unwrap();
+    println!("{}", n);
+}
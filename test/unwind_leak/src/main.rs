@@ -0,0 +1,18 @@
+// unwind leak with --unwind-aware: a panic of `unwrap()` skips the raw unlock,
+// and the mutex stays locked for good
+
+use lock_api::RawMutex as _;
+use parking_lot::Mutex;
+
+fn update(m: &Mutex<Vec<i32>>, v: Option<i32>) {
+    let log = String::from("update");
+    unsafe { m.raw().lock() };
+    let v = v.unwrap();
+    println!("{}: {}", log, v);
+    unsafe { m.raw().unlock() };
+}
+
+fn main() {
+    let m = Mutex::new(vec![]);
+    update(&m, Some(1));
+}